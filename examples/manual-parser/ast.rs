@@ -1,5 +1,6 @@
 #[derive(Debug)]
+#[allow(dead_code)] // Only read through Debug
 pub enum AstTerm {
     Number,
-    Paren(Box<AstTerm>)
-}
\ No newline at end of file
+    Paren(Box<AstTerm>),
+}
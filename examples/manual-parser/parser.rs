@@ -33,6 +33,7 @@ enum ActionResult {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)] // Only read through Debug
 pub enum ParseError {
     UnexpectedEOF,
     ExtraToken(Token),
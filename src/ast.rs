@@ -2,13 +2,68 @@
 pub struct AstGrammar<'input> {
     pub imports: Vec<&'input str>,
     pub token_decl: AstTokenDecl<'input>,
+    pub grammar_decl: AstGrammarDecl<'input>,
+    pub extern_decl: AstExternDecl<'input>,
     pub nonterminals: Vec<AstNonterminal<'input>>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct AstTokenDecl<'input> {
-    pub name: &'input str,
+    pub ty: AstTypeRef<'input>,
     pub aliases: Vec<AstTokenAlias<'input>>,
+    /// The token the lexer produces for invalid input (`error = Token::Error`)
+    pub error_pattern: Option<AstTokenPattern<'input>>,
+}
+
+/// The `grammar<...> where ...;` declaration
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct AstGrammarDecl<'input> {
+    /// The generic parameters, which every generated item is declared with
+    pub generics: Vec<AstGenericParam<'input>>,
+    /// The parameters passed to the entry points and every action
+    pub params: Vec<AstGrammarParam<'input>>,
+    pub where_clauses: Vec<AstWherePredicate<'input>>,
+}
+
+/// A grammar parameter (`ctx: &mut Ctx`)
+#[derive(Debug, Eq, PartialEq)]
+pub struct AstGrammarParam<'input> {
+    pub name: &'input str,
+    pub ty: AstTypeRef<'input>,
+}
+
+/// A lifetime or type parameter (`'input`, `T: Copy`)
+#[derive(Debug, Eq, PartialEq)]
+pub struct AstGenericParam<'input> {
+    /// Lifetime names include the `'`
+    pub name: &'input str,
+    pub bounds: Vec<AstTypeRef<'input>>,
+}
+
+impl AstGenericParam<'_> {
+    pub fn is_lifetime(&self) -> bool {
+        self.name.starts_with('\'')
+    }
+}
+
+/// A bound in the where clause (`T: Display + 'input`)
+#[derive(Debug, Eq, PartialEq)]
+pub struct AstWherePredicate<'input> {
+    pub ty: AstTypeRef<'input>,
+    pub bounds: Vec<AstTypeRef<'input>>,
+}
+
+/// The types declared in the `extern { ... }` block
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct AstExternDecl<'input> {
+    /// The error type returned by fallible actions (`type Error = MyError;`)
+    pub error_ty: Option<AstTypeRef<'input>>,
+    /// The location type of a spanned lexer (`type Location = usize;`), which
+    /// yields `(Location, Token, Location)` triples
+    pub location_ty: Option<AstTypeRef<'input>>,
+    /// The error type of a fallible lexer (`type LexerError = LexError;`),
+    /// which yields `Result`s
+    pub lexer_error_ty: Option<AstTypeRef<'input>>,
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -17,24 +72,155 @@ pub struct AstTokenAlias<'input> {
     pub pattern: AstTokenPattern<'input>,
 }
 
+/// A Rust pattern which matches a token
 #[derive(Debug, Eq, PartialEq)]
-pub struct AstTokenPattern<'input> {
-    pub ty: &'input str,
-    pub variant: &'input str,
+pub enum AstTokenPattern<'input> {
+    /// A unit, tuple, or struct variant, matched regardless of its fields when
+    /// used as the whole pattern (`Token::Plus`)
+    Path(AstTypePath<'input>),
+    TupleStruct(AstTypePath<'input>, Vec<AstTokenPattern<'input>>),
+    Struct(AstTypePath<'input>, Vec<AstFieldPattern<'input>>),
+    Tuple(Vec<AstTokenPattern<'input>>),
+    /// A string, char, or number literal
+    Literal(&'input str),
+    Wildcard,
+    /// The `..` in tuple patterns
+    Rest,
+    /// Part of the token which is passed to the actions (`<i64>`)
+    Payload(AstTypeRef<'input>),
+    Or(Vec<AstTokenPattern<'input>>),
+}
+
+impl<'input> AstTokenPattern<'input> {
+    #[cfg(test)]
+    pub fn simple_path(segments: Vec<&'input str>) -> Self {
+        AstTokenPattern::Path(AstTypePath {
+            is_absolute: false,
+            segments,
+        })
+    }
+}
+
+impl<'input> AstTokenPattern<'input> {
+    /// Get the types of the payloads (`<T>`) in the order they are bound.
+    /// Every alternative of an or-pattern has to bind the same payloads (see
+    /// `has_consistent_payloads`), so only the first alternative is checked.
+    pub fn payload_tys(&self) -> Vec<&AstTypeRef<'input>> {
+        match self {
+            AstTokenPattern::Payload(ty) => vec![ty],
+            AstTokenPattern::TupleStruct(_, patterns) | AstTokenPattern::Tuple(patterns) => {
+                patterns.iter().flat_map(Self::payload_tys).collect()
+            }
+            AstTokenPattern::Struct(_, fields) => fields
+                .iter()
+                .flat_map(|field| match field {
+                    AstFieldPattern::Field(_, pattern) => pattern.payload_tys(),
+                    AstFieldPattern::Rest => Vec::new(),
+                })
+                .collect(),
+            AstTokenPattern::Or(alternatives) => alternatives[0].payload_tys(),
+            AstTokenPattern::Path(_)
+            | AstTokenPattern::Literal(_)
+            | AstTokenPattern::Wildcard
+            | AstTokenPattern::Rest => Vec::new(),
+        }
+    }
+
+    /// Check that the alternatives of every or-pattern bind the same payload
+    /// types, so they can be unwrapped into one value
+    pub fn has_consistent_payloads(&self) -> bool {
+        match self {
+            AstTokenPattern::Or(alternatives) => {
+                alternatives.iter().all(Self::has_consistent_payloads)
+                    && alternatives
+                        .windows(2)
+                        .all(|pair| pair[0].payload_tys() == pair[1].payload_tys())
+            }
+            AstTokenPattern::TupleStruct(_, patterns) | AstTokenPattern::Tuple(patterns) => {
+                patterns.iter().all(Self::has_consistent_payloads)
+            }
+            AstTokenPattern::Struct(_, fields) => fields.iter().all(|field| match field {
+                AstFieldPattern::Field(_, pattern) => pattern.has_consistent_payloads(),
+                AstFieldPattern::Rest => true,
+            }),
+            AstTokenPattern::Path(_)
+            | AstTokenPattern::Literal(_)
+            | AstTokenPattern::Wildcard
+            | AstTokenPattern::Rest
+            | AstTokenPattern::Payload(_) => true,
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AstFieldPattern<'input> {
+    Field(&'input str, AstTokenPattern<'input>),
+    /// The `..` in struct patterns
+    Rest,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct AstNonterminal<'input> {
+    /// The doc comments (`/// ...`) on the nonterminal, without the slashes
+    pub docs: Vec<&'input str>,
+    pub attrs: Vec<AstAttribute<'input>>,
     pub is_pub: bool,
     pub name: &'input str,
+    /// The symbol parameters of a macro (`Comma<T>`). Macros are expanded
+    /// for each use before table generation.
+    pub params: Vec<&'input str>,
+    /// The inputs which are passed down to the nonterminal where it's used
+    /// (`Declarator(base: Type)`)
+    pub inputs: Vec<AstGrammarParam<'input>>,
+    /// The nonterminal whose inputs can be used in the actions, for the
+    /// nonterminals generated from the productions of one with inputs
+    pub input_scope: Option<&'input str>,
     pub ty: AstTypeRef<'input>,
     pub productions: Vec<AstProduction<'input>>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum AstTypeRef<'input> {
     Ty(AstTypePath<'input>, Vec<AstTypeRef<'input>>),
     Tuple(Vec<AstTypeRef<'input>>),
+    Ref {
+        lifetime: Option<&'input str>,
+        is_mut: bool,
+        ty: Box<AstTypeRef<'input>>,
+    },
+    /// A path type which can't be written as `Ty`, because it has a qualified
+    /// self type (`<T as Trait>::Output`), or generics on a segment other than
+    /// the last (`a::B<T>::C`) or in parentheses (`Fn(i32) -> i32`)
+    Path {
+        qself: Option<AstQSelf<'input>>,
+        is_absolute: bool,
+        segments: Vec<AstPathSegment<'input>>,
+    },
+    /// A type in parentheses (`(dyn Trait + Send)`), as opposed to a tuple
+    Paren(Box<AstTypeRef<'input>>),
+    Slice(Box<AstTypeRef<'input>>),
+    /// An array type and its length, which is a number or const parameter
+    Array(Box<AstTypeRef<'input>>, &'input str),
+    Ptr {
+        is_mut: bool,
+        ty: Box<AstTypeRef<'input>>,
+    },
+    FnPtr {
+        is_unsafe: bool,
+        /// The ABI string literal of an `extern` fn (`extern "C" fn()`)
+        abi: Option<&'input str>,
+        params: Vec<AstTypeRef<'input>>,
+        ret: Option<Box<AstTypeRef<'input>>>,
+    },
+    /// A trait object and its bounds (`dyn Trait + 'a`)
+    DynTrait(Vec<AstTypeRef<'input>>),
+    /// An anonymous type and its bounds (`impl Trait + 'a`)
+    ImplTrait(Vec<AstTypeRef<'input>>),
+    Never,
+    /// A lifetime used as a generic argument or bound (`'input`)
+    Lifetime(&'input str),
+    /// An associated type binding used as a generic argument (`Item = T`)
+    AssocType(&'input str, Box<AstTypeRef<'input>>),
 }
 
 impl<'input> AstTypeRef<'input> {
@@ -48,9 +234,110 @@ impl<'input> AstTypeRef<'input> {
             Vec::new(),
         )
     }
+
+    /// Create a path type, which is a `Ty` unless it needs the full generality
+    /// of a `Path`
+    pub fn path(
+        qself: Option<AstQSelf<'input>>,
+        is_absolute: bool,
+        mut segments: Vec<AstPathSegment<'input>>,
+    ) -> Self {
+        let (last, init) = segments.split_last().unwrap();
+        let is_simple = qself.is_none()
+            && init
+                .iter()
+                .all(|segment| segment.args == AstGenericArgs::None)
+            && !matches!(last.args, AstGenericArgs::Parenthesized { .. });
+
+        if !is_simple {
+            return AstTypeRef::Path {
+                qself,
+                is_absolute,
+                segments,
+            };
+        }
+
+        let generics = match segments.last_mut().map(|last| &mut last.args) {
+            Some(AstGenericArgs::AngleBracketed(generics)) => std::mem::take(generics),
+            _ => Vec::new(),
+        };
+        AstTypeRef::Ty(
+            AstTypePath {
+                is_absolute,
+                segments: segments.iter().map(|segment| segment.name).collect(),
+            },
+            generics,
+        )
+    }
+
+    /// Check if the type refers to a generic parameter
+    pub fn mentions(&self, param: &str) -> bool {
+        let any_mentions = |tys: &[AstTypeRef]| tys.iter().any(|ty| ty.mentions(param));
+
+        match self {
+            AstTypeRef::Ty(path, generics) => path.segments == [param] || any_mentions(generics),
+            AstTypeRef::Path {
+                qself, segments, ..
+            } => {
+                qself.as_ref().is_some_and(|qself| {
+                    qself.ty.mentions(param)
+                        || qself
+                            .as_trait
+                            .as_ref()
+                            .is_some_and(|as_trait| as_trait.mentions(param))
+                }) || segments.iter().any(|segment| match &segment.args {
+                    AstGenericArgs::None => false,
+                    AstGenericArgs::AngleBracketed(generics) => any_mentions(generics),
+                    AstGenericArgs::Parenthesized { inputs, output } => {
+                        any_mentions(inputs) || output.as_ref().is_some_and(|ty| ty.mentions(param))
+                    }
+                })
+            }
+            AstTypeRef::Tuple(tys) | AstTypeRef::DynTrait(tys) | AstTypeRef::ImplTrait(tys) => {
+                any_mentions(tys)
+            }
+            AstTypeRef::Ref { lifetime, ty, .. } => *lifetime == Some(param) || ty.mentions(param),
+            AstTypeRef::Paren(ty)
+            | AstTypeRef::Slice(ty)
+            | AstTypeRef::Ptr { ty, .. }
+            | AstTypeRef::AssocType(_, ty) => ty.mentions(param),
+            AstTypeRef::Array(ty, len) => *len == param || ty.mentions(param),
+            AstTypeRef::FnPtr { params, ret, .. } => {
+                any_mentions(params) || ret.as_ref().is_some_and(|ty| ty.mentions(param))
+            }
+            AstTypeRef::Never => false,
+            AstTypeRef::Lifetime(lifetime) => *lifetime == param,
+        }
+    }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// The self type of a qualified path, and the trait it's cast to
+/// (`<T as Trait>`)
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AstQSelf<'input> {
+    pub ty: Box<AstTypeRef<'input>>,
+    pub as_trait: Option<Box<AstTypeRef<'input>>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AstPathSegment<'input> {
+    pub name: &'input str,
+    pub args: AstGenericArgs<'input>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AstGenericArgs<'input> {
+    None,
+    /// `Vec<T>` (or the turbofish `Vec::<T>`)
+    AngleBracketed(Vec<AstTypeRef<'input>>),
+    /// The arguments of the `Fn` traits (`Fn(A, B) -> C`)
+    Parenthesized {
+        inputs: Vec<AstTypeRef<'input>>,
+        output: Option<Box<AstTypeRef<'input>>>,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct AstTypePath<'input> {
     pub is_absolute: bool,
     pub segments: Vec<&'input str>,
@@ -58,13 +345,141 @@ pub struct AstTypePath<'input> {
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct AstProduction<'input> {
+    pub docs: Vec<&'input str>,
+    pub attrs: Vec<AstAttribute<'input>>,
+    /// The predicates which have to hold for the production to be chosen.
+    /// Productions guarded by predicates can share a parse-table cell, and
+    /// are tried in order.
+    pub predicates: Vec<AstPredicate<'input>>,
     pub symbols: Vec<AstSymbol<'input>>,
+    pub action: AstAction<'input>,
+}
+
+/// A check on the upcoming input, which guards a production
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AstPredicate<'input> {
+    /// A Rust expression which can use the grammar parameters and the peeked
+    /// token, as `token: Option<&Token>` (`&{ is_type_name(token) }`)
+    Semantic(&'input str),
+    /// The terminals which the next tokens have to match (`&("IDENT" "=")`)
+    Syntactic(Vec<&'input str>),
+}
+
+/// An attribute on a nonterminal or production (`#[cfg(feature = "x")]`)
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AstAttribute<'input> {
+    /// The path naming the attribute (`cfg`)
+    pub name: &'input str,
+    /// The attribute's contents, including its name
     pub code: &'input str,
 }
 
+impl<'input> AstAttribute<'input> {
+    pub fn new(code: &'input str) -> Self {
+        let name_len = code
+            .find(|c: char| !c.is_alphanumeric() && c != '_' && c != ':')
+            .unwrap_or(code.len());
+
+        AstAttribute {
+            name: &code[..name_len],
+            code,
+        }
+    }
+
+    /// Check if the attribute is conditional compilation (`cfg`)
+    pub fn is_cfg(&self) -> bool {
+        self.name == "cfg"
+    }
+
+    /// Check if the attribute is a standard one which is copied onto the
+    /// generated functions: conditional compilation, docs, lint levels and
+    /// `inline`. Any other attribute is only kept in the grammar.
+    pub fn is_standard(&self) -> bool {
+        matches!(
+            self.name,
+            "cfg" | "cfg_attr" | "doc" | "allow" | "warn" | "deny" | "forbid" | "expect" | "inline"
+        )
+    }
+}
+
+/// What happens when a production is reduced
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AstAction<'input> {
+    /// Run the user's action code
+    Code(&'input str),
+    /// Run the user's action code, which returns a `Result` (`=>?`)
+    FallibleCode(&'input str),
+    /// Produce the current location, for the nonterminals which `@L` and `@R`
+    /// are lowered to
+    Location(AstLocation),
+    /// Collect the values of a repetition into a `Vec`. The field is the
+    /// repeated symbol.
+    Collect(&'input str),
+    /// Don't reduce, leaving the values for an enclosing `Collect` or `Fold`
+    Passthrough,
+    /// Reduce the values of the production's symbols, which were parsed
+    /// before this (empty) production, into a value of the named nonterminal.
+    /// Operator precedence levels and `fold(...)` are lowered to these, so
+    /// each operator is reduced as soon as its operands are parsed.
+    Fold(&'input str, Box<AstProduction<'input>>),
+    /// No action code was given, so the production returns its selected
+    /// value, or a tuple of them if there are several
+    Implicit,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub enum AstSymbol<'input> {
     Terminal(&'input str),
     Nonterminal(&'input str),
-    Named(&'input str, &'input str),
+    /// A symbol bound to a name (`<name:Symbol>`)
+    Named(&'input str, Box<AstSymbol<'input>>),
+    /// A symbol selected without a name (`<Symbol>`)
+    Selected(Box<AstSymbol<'input>>),
+    /// A repeated or optional symbol (`Symbol*`, `Symbol+`, `Symbol?`)
+    Repeat(AstRepeatOp, Box<AstSymbol<'input>>),
+    /// A parenthesized group of alternatives (`(A B | C)`)
+    Group(Vec<Vec<AstSymbol<'input>>>),
+    /// A use of a macro nonterminal (`Comma<Expr>`)
+    Macro(&'input str, Vec<AstSymbol<'input>>),
+    /// A location pseudo-symbol (`@L`, `@R`)
+    Location(AstLocation),
+    /// A mid-rule action (`{ code }`), which runs when the parser reaches it.
+    /// Its value is `()` unless a type is given (`<name:{ code }: Type>`).
+    Action(&'input str, Option<AstTypeRef<'input>>),
+    /// A use of a nonterminal with inputs, and the Rust code of its arguments
+    /// (`Declarator(ty.clone())`)
+    Call(&'input str, &'input str),
+    /// A left fold over a repeated symbol
+    /// (`fold(init: Term, ("+" Term) => |acc, rhs| acc + rhs.1)`)
+    Fold(Box<AstFold<'input>>),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct AstFold<'input> {
+    /// The symbol which gives the initial value
+    pub init: AstSymbol<'input>,
+    /// The symbol which is repeated and folded into the value
+    pub step: AstSymbol<'input>,
+    /// The closure's parameters, for the value so far and the step's value
+    pub params: (&'input str, &'input str),
+    /// The closure's body, as `Code` or `FallibleCode`
+    pub action: AstAction<'input>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AstLocation {
+    /// `@L`, the start of the next symbol
+    Start,
+    /// `@R`, the end of the previous symbol
+    End,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum AstRepeatOp {
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+    /// `?`
+    Optional,
 }
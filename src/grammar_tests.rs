@@ -1,6 +1,8 @@
 use crate::ast::{
-    AstGrammar, AstNonterminal, AstProduction, AstSymbol, AstTokenAlias, AstTokenDecl,
-    AstTokenPattern, AstTypePath, AstTypeRef,
+    AstAction, AstAttribute, AstExternDecl, AstFieldPattern, AstFold, AstGenericArgs,
+    AstGenericParam, AstGrammar, AstGrammarDecl, AstGrammarParam, AstLocation, AstNonterminal,
+    AstPathSegment, AstPredicate, AstProduction, AstQSelf, AstRepeatOp, AstSymbol, AstTokenAlias,
+    AstTokenDecl, AstTokenPattern, AstTypePath, AstTypeRef, AstWherePredicate,
 };
 
 macro_rules! parse_grammar {
@@ -33,9 +35,12 @@ fn empty_grammar() {
         AstGrammar {
             imports: Vec::new(),
             token_decl: AstTokenDecl {
-                name: "Token",
-                aliases: Vec::new()
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
             },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
             nonterminals: Vec::new()
         }
     };
@@ -55,31 +60,25 @@ fn token_aliases() {
         AstGrammar {
             imports: Vec::new(),
             token_decl: AstTokenDecl {
-                name: "Token",
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
                 aliases: vec![
                     AstTokenAlias{
                         term: "\"(\"",
-                        pattern: AstTokenPattern{
-                            ty: "Token",
-                            variant: "LParen"
-                        }
+                        pattern: AstTokenPattern::simple_path(vec!["Token", "LParen"])
                     },
                     AstTokenAlias {
                         term: "\")\"",
-                        pattern: AstTokenPattern {
-                            ty: "Token",
-                            variant: "RParen"
-                        }
+                        pattern: AstTokenPattern::simple_path(vec!["Token", "RParen"])
                     },
                     AstTokenAlias {
                         term: "\"NUMBER\"",
-                        pattern: AstTokenPattern {
-                            ty: "Token",
-                            variant: "Number"
-                        }
+                        pattern: AstTokenPattern::simple_path(vec!["Token", "Number"])
                     }
-                ]
+                ],
+                error_pattern: None
             },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
             nonterminals: Vec::new()
         }
     }
@@ -101,14 +100,17 @@ fn imports() {
         },
         AstGrammar {
             imports: vec![
-                "crate :: Token",
-                "std :: collections :: { HashMap, HashSet }",
-                "std\n:: { self, iter :: { self, once } }"
+                "crate::Token",
+                "std::collections::{HashMap, HashSet}",
+                "std::{ self, iter::{self, once} }"
             ],
             token_decl: AstTokenDecl {
-                name: "Token",
-                aliases: Vec::new()
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
             },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
             nonterminals: Vec::new()
         }
     }
@@ -131,45 +133,78 @@ fn nonterminals() {
         AstGrammar {
             imports: Vec::new(),
             token_decl: AstTokenDecl {
-                name: "Token",
-                aliases: Vec::new()
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
             },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
             nonterminals: vec![
                 AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
                     is_pub: true,
                     name: "Start",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
                     ty: AstTypeRef::Tuple(Vec::new()),
                     productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
                         symbols: vec![AstSymbol::Terminal("\"token1\""), AstSymbol::Nonterminal("Nonterm1")],
-                        code: "()"
+                        action: AstAction::Code("()")
                     }]
                 },
                 AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
                     is_pub: false,
                     name: "Nonterm1",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
                     ty: AstTypeRef::Tuple(Vec::new()),
                     productions: vec![
                         AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
                             symbols: vec![AstSymbol::Terminal("\"token2\"")],
-                            code: "()"
+                            action: AstAction::Code("()")
                         },
                         AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
                             symbols: vec![AstSymbol::Terminal("\"token3\""), AstSymbol::Terminal("\"token4\"")],
-                            code: "()"
+                            action: AstAction::Code("()")
                         },
                         AstProduction {
-                            symbols: vec![AstSymbol::Named("var", "Nonterm2")],
-                            code: "var"
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![AstSymbol::Named("var", Box::new(AstSymbol::Nonterminal("Nonterm2")))],
+                            action: AstAction::Code("var")
                         }
                     ]
                 },
                 AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
                     is_pub: false,
                     name: "Nonterm2",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
                     ty: AstTypeRef::Tuple(Vec::new()),
                     productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
                         symbols: vec![AstSymbol::Terminal("\"token5\"")],
-                        code: "()"
+                        action: AstAction::Code("()")
                     }]
                 }
             ]
@@ -190,34 +225,58 @@ fn nonterminal_types() {
         AstGrammar {
             imports: Vec::new(),
             token_decl: AstTokenDecl {
-                name: "Token",
-                aliases: Vec::new()
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
             },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
             nonterminals: vec![
                 AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
                     is_pub: true,
                     name: "Start",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
                     ty: AstTypeRef::Tuple(vec![
                         AstTypeRef::simple_ty(vec!["usize"]),
                         AstTypeRef::simple_ty(vec!["String"]),
                     ]),
-                    productions: vec![AstProduction{
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
                         symbols: vec![AstSymbol::Terminal("\"token1\"")],
-                        code: "(1, \"test\" . to_string())"
+                        action: AstAction::Code("(1, \"test\".to_string())")
                     }]
                 },
                 AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
                     is_pub: false,
                     name: "Nonterm1",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
                     ty: AstTypeRef::simple_ty(vec!["crate", "lexer", "Token"]),
                     productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
                         symbols: vec![AstSymbol::Terminal("\"token2\"")],
-                        code: "Token :: LParen"
+                        action: AstAction::Code("Token::LParen")
                     }]
                 },
                 AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
                     is_pub: false,
                     name: "Nonterm2",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
                     ty: AstTypeRef::Ty(
                         AstTypePath {
                             is_absolute: true,
@@ -229,11 +288,1237 @@ fn nonterminal_types() {
                         ]
                     ),
                     productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
                         symbols: vec![AstSymbol::Terminal("\"token3\"")],
-                        code: "HashMap :: new()"
+                        action: AstAction::Code("HashMap::new()")
+                    }]
+                }
+            ]
+        }
+    }
+}
+
+#[test]
+fn token_payloads() {
+    grammar_test! {
+        grammar {
+            token Token {
+                "NUMBER" = Token::Number(<i64>),
+            }
+            grammar;
+            pub Start: i64 = <n:"NUMBER"> => n;
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: vec![AstTokenAlias {
+                    term: "\"NUMBER\"",
+                    pattern: AstTokenPattern::TupleStruct(
+                        AstTypePath {
+                            is_absolute: false,
+                            segments: vec!["Token", "Number"]
+                        },
+                        vec![AstTokenPattern::Payload(AstTypeRef::simple_ty(vec!["i64"]))]
+                    )
+                }],
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Start",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::simple_ty(vec!["i64"]),
+                productions: vec![AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![AstSymbol::Named("n", Box::new(AstSymbol::Terminal("\"NUMBER\"")))],
+                    action: AstAction::Code("n")
+                }]
+            }]
+        }
+    }
+}
+
+#[test]
+fn token_patterns() {
+    grammar_test! {
+        grammar {
+            token Token {
+                "IDENT" = crate::lex::Token::Ident(..),
+                "OP" = Token::Op('+' | '-'),
+                "SIGN" = Token::Plus | Token::Minus,
+                "INT" = Token::Int { value: <u64>, radix: 10, .. },
+                "PAIR" = Token::Pair(<i32>, _),
+            }
+            grammar;
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: vec![
+                    AstTokenAlias {
+                        term: "\"IDENT\"",
+                        pattern: AstTokenPattern::TupleStruct(
+                            AstTypePath {
+                                is_absolute: false,
+                                segments: vec!["crate", "lex", "Token", "Ident"]
+                            },
+                            vec![AstTokenPattern::Rest]
+                        )
+                    },
+                    AstTokenAlias {
+                        term: "\"OP\"",
+                        pattern: AstTokenPattern::TupleStruct(
+                            AstTypePath {
+                                is_absolute: false,
+                                segments: vec!["Token", "Op"]
+                            },
+                            vec![AstTokenPattern::Or(vec![
+                                AstTokenPattern::Literal("'+'"),
+                                AstTokenPattern::Literal("'-'"),
+                            ])]
+                        )
+                    },
+                    AstTokenAlias {
+                        term: "\"SIGN\"",
+                        pattern: AstTokenPattern::Or(vec![
+                            AstTokenPattern::simple_path(vec!["Token", "Plus"]),
+                            AstTokenPattern::simple_path(vec!["Token", "Minus"]),
+                        ])
+                    },
+                    AstTokenAlias {
+                        term: "\"INT\"",
+                        pattern: AstTokenPattern::Struct(
+                            AstTypePath {
+                                is_absolute: false,
+                                segments: vec!["Token", "Int"]
+                            },
+                            vec![
+                                AstFieldPattern::Field(
+                                    "value",
+                                    AstTokenPattern::Payload(AstTypeRef::simple_ty(vec!["u64"]))
+                                ),
+                                AstFieldPattern::Field("radix", AstTokenPattern::Literal("10")),
+                                AstFieldPattern::Rest,
+                            ]
+                        )
+                    },
+                    AstTokenAlias {
+                        term: "\"PAIR\"",
+                        pattern: AstTokenPattern::TupleStruct(
+                            AstTypePath {
+                                is_absolute: false,
+                                segments: vec!["Token", "Pair"]
+                            },
+                            vec![
+                                AstTokenPattern::Payload(AstTypeRef::simple_ty(vec!["i32"])),
+                                AstTokenPattern::Wildcard,
+                            ]
+                        )
+                    },
+                ],
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: Vec::new()
+        }
+    }
+}
+
+#[test]
+fn repetitions() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+            pub Start: () = "a"* <b:B+> C? => ();
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Start",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::Tuple(Vec::new()),
+                productions: vec![AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![
+                        AstSymbol::Repeat(
+                            AstRepeatOp::ZeroOrMore,
+                            Box::new(AstSymbol::Terminal("\"a\""))
+                        ),
+                        AstSymbol::Named(
+                            "b",
+                            Box::new(AstSymbol::Repeat(
+                                AstRepeatOp::OneOrMore,
+                                Box::new(AstSymbol::Nonterminal("B"))
+                            ))
+                        ),
+                        AstSymbol::Repeat(
+                            AstRepeatOp::Optional,
+                            Box::new(AstSymbol::Nonterminal("C"))
+                        ),
+                    ],
+                    action: AstAction::Code("()")
+                }]
+            }]
+        }
+    }
+}
+
+#[test]
+fn undefined_symbols() {
+    let lower = |nonterminals: &str| {
+        let grammar = format!(
+            "token Token {{ \"a\" = Token::A }} grammar; {}",
+            nonterminals
+        );
+        let mut ast = crate::parsing::parse(&grammar).expect("Grammar should parse");
+        crate::ll_table_gen::lower_ebnf(&mut ast)
+    };
+
+    assert_eq!(
+        lower("A: () = Undefined* => ();"),
+        Err("Unknown symbol Undefined".to_string())
+    );
+    assert_eq!(
+        lower("A: () = Undefined? => ();"),
+        Err("Unknown symbol Undefined".to_string())
+    );
+    assert_eq!(
+        lower("A: () = (Undefined | \"a\") => ();"),
+        Err("Unknown symbol Undefined".to_string())
+    );
+}
+
+#[test]
+fn groups() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+            pub Start: () = ("a" | B C) (<d:D>)? => ();
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Start",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::Tuple(Vec::new()),
+                productions: vec![AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![
+                        AstSymbol::Group(vec![
+                            vec![AstSymbol::Terminal("\"a\"")],
+                            vec![AstSymbol::Nonterminal("B"), AstSymbol::Nonterminal("C")],
+                        ]),
+                        AstSymbol::Repeat(
+                            AstRepeatOp::Optional,
+                            Box::new(AstSymbol::Group(vec![vec![AstSymbol::Named(
+                                "d",
+                                Box::new(AstSymbol::Nonterminal("D"))
+                            )]]))
+                        ),
+                    ],
+                    action: AstAction::Code("()")
+                }]
+            }]
+        }
+    }
+}
+
+#[test]
+fn macros() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+            pub Start: Vec<u32> = Comma<Num> => ();
+            Comma<T>: Vec<T> = <v:T*> => v;
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: true,
+                    name: "Start",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::Ty(
+                        AstTypePath {
+                            is_absolute: false,
+                            segments: vec!["Vec"]
+                        },
+                        vec![AstTypeRef::simple_ty(vec!["u32"])]
+                    ),
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![AstSymbol::Macro("Comma", vec![AstSymbol::Nonterminal("Num")])],
+                        action: AstAction::Code("()")
+                    }]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "Comma",
+                    params: vec!["T"],
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::Ty(
+                        AstTypePath {
+                            is_absolute: false,
+                            segments: vec!["Vec"]
+                        },
+                        vec![AstTypeRef::simple_ty(vec!["T"])]
+                    ),
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![AstSymbol::Named(
+                            "v",
+                            Box::new(AstSymbol::Repeat(
+                                AstRepeatOp::ZeroOrMore,
+                                Box::new(AstSymbol::Nonterminal("T"))
+                            ))
+                        )],
+                        action: AstAction::Code("v")
                     }]
                 }
             ]
         }
     }
 }
+
+#[test]
+fn implicit_actions() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+            pub Start: (A, B) = {
+                "(" <A> <B> ")",
+                <a:A> "," <B> => (a, <>.1),
+            };
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Start",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::Tuple(vec![
+                    AstTypeRef::simple_ty(vec!["A"]),
+                    AstTypeRef::simple_ty(vec!["B"]),
+                ]),
+                productions: vec![
+                    AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![
+                            AstSymbol::Terminal("\"(\""),
+                            AstSymbol::Selected(Box::new(AstSymbol::Nonterminal("A"))),
+                            AstSymbol::Selected(Box::new(AstSymbol::Nonterminal("B"))),
+                            AstSymbol::Terminal("\")\""),
+                        ],
+                        action: AstAction::Implicit
+                    },
+                    AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![
+                            AstSymbol::Named("a", Box::new(AstSymbol::Nonterminal("A"))),
+                            AstSymbol::Terminal("\",\""),
+                            AstSymbol::Selected(Box::new(AstSymbol::Nonterminal("B"))),
+                        ],
+                        action: AstAction::Code("(a, <>.1)")
+                    },
+                ]
+            }]
+        }
+    }
+}
+
+#[test]
+fn comments() {
+    let grammar = r#"
+        // Imports
+        use crate::Token; /* the lexer's token */
+        use std::collections::{
+            HashMap, // Used in actions
+        };
+
+        /* Block comments /* can be nested */ */
+        token Token {
+            "a" = Token::A, // Letter A
+        }
+        grammar;
+
+        /// The start
+        /// nonterminal
+        pub Start: () = {
+            /// Just "a"
+            "a" => (), // Trailing comment
+            // Comment in the alternatives
+            Start /* inline */ => { /* ( */ () },
+        };
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+
+    assert_eq!(
+        actual_ast,
+        AstGrammar {
+            imports: vec![
+                "crate::Token",
+                "std::collections::{\n            HashMap, // Used in actions\n        }"
+            ],
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: vec![AstTokenAlias {
+                    term: "\"a\"",
+                    pattern: AstTokenPattern::simple_path(vec!["Token", "A"])
+                }],
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: vec![" The start", " nonterminal"],
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Start",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::Tuple(Vec::new()),
+                productions: vec![
+                    AstProduction {
+                        docs: vec![" Just \"a\""],
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![AstSymbol::Terminal("\"a\"")],
+                        action: AstAction::Code("()")
+                    },
+                    AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![AstSymbol::Nonterminal("Start")],
+                        action: AstAction::Code("{ /* ( */ () }")
+                    }
+                ]
+            }]
+        }
+    );
+}
+
+#[test]
+fn grammar_generics() {
+    grammar_test! {
+        grammar {
+            token Token<'input> {
+                "IDENT" = Token::Ident(<&'input str>),
+            }
+            grammar<'input, T: Clone + From<&'input str>> where T: 'input;
+
+            pub Ident: &'input T = "IDENT" => todo!();
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::Ty(
+                    AstTypePath {
+                        is_absolute: false,
+                        segments: vec!["Token"]
+                    },
+                    vec![AstTypeRef::Lifetime("'input")]
+                ),
+                aliases: vec![AstTokenAlias {
+                    term: "\"IDENT\"",
+                    pattern: AstTokenPattern::TupleStruct(
+                        AstTypePath {
+                            is_absolute: false,
+                            segments: vec!["Token", "Ident"]
+                        },
+                        vec![AstTokenPattern::Payload(AstTypeRef::Ref {
+                            lifetime: Some("'input"),
+                            is_mut: false,
+                            ty: Box::new(AstTypeRef::simple_ty(vec!["str"]))
+                        })]
+                    )
+                }],
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl {
+                generics: vec![
+                    AstGenericParam {
+                        name: "'input",
+                        bounds: Vec::new()
+                    },
+                    AstGenericParam {
+                        name: "T",
+                        bounds: vec![
+                            AstTypeRef::simple_ty(vec!["Clone"]),
+                            AstTypeRef::Ty(
+                                AstTypePath {
+                                    is_absolute: false,
+                                    segments: vec!["From"]
+                                },
+                                vec![AstTypeRef::Ref {
+                                    lifetime: Some("'input"),
+                                    is_mut: false,
+                                    ty: Box::new(AstTypeRef::simple_ty(vec!["str"]))
+                                }]
+                            )
+                        ]
+                    }
+                ],
+                params: Vec::new(),
+                where_clauses: vec![AstWherePredicate {
+                    ty: AstTypeRef::simple_ty(vec!["T"]),
+                    bounds: vec![AstTypeRef::Lifetime("'input")]
+                }]
+            },
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Ident",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::Ref {
+                    lifetime: Some("'input"),
+                    is_mut: false,
+                    ty: Box::new(AstTypeRef::simple_ty(vec!["T"]))
+                },
+                productions: vec![AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![AstSymbol::Terminal("\"IDENT\"")],
+                    action: AstAction::Code("todo!()")
+                }]
+            }]
+        }
+    };
+}
+
+#[test]
+fn grammar_params() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar(names: &mut Vec<String>, depth: usize);
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl {
+                generics: Vec::new(),
+                params: vec![
+                    AstGrammarParam {
+                        name: "names",
+                        ty: AstTypeRef::Ref {
+                            lifetime: None,
+                            is_mut: true,
+                            ty: Box::new(AstTypeRef::Ty(
+                                AstTypePath {
+                                    is_absolute: false,
+                                    segments: vec!["Vec"]
+                                },
+                                vec![AstTypeRef::simple_ty(vec!["String"])]
+                            ))
+                        }
+                    },
+                    AstGrammarParam {
+                        name: "depth",
+                        ty: AstTypeRef::simple_ty(vec!["usize"])
+                    }
+                ],
+                where_clauses: Vec::new()
+            },
+            extern_decl: AstExternDecl::default(),
+            nonterminals: Vec::new()
+        }
+    };
+}
+
+#[test]
+fn fallible_actions() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+
+            extern {
+                type Error = crate::Error;
+            }
+
+            Start: i32 = =>? Err(crate::Error);
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl {
+                error_ty: Some(AstTypeRef::simple_ty(vec!["crate", "Error"])),
+                location_ty: None,
+                lexer_error_ty: None
+            },
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: false,
+                name: "Start",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::simple_ty(vec!["i32"]),
+                productions: vec![AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: Vec::new(),
+                    action: AstAction::FallibleCode("Err(crate::Error)")
+                }]
+            }]
+        }
+    };
+}
+
+#[test]
+fn locations() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+            pub Span: (usize, usize) = <l:@L> "a" <r:@R> => (l, r);
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: vec![AstNonterminal {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                is_pub: true,
+                name: "Span",
+                params: Vec::new(),
+                inputs: Vec::new(),
+                input_scope: None,
+                ty: AstTypeRef::Tuple(vec![
+                    AstTypeRef::simple_ty(vec!["usize"]),
+                    AstTypeRef::simple_ty(vec!["usize"]),
+                ]),
+                productions: vec![AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![
+                        AstSymbol::Named("l", Box::new(AstSymbol::Location(AstLocation::Start))),
+                        AstSymbol::Terminal("\"a\""),
+                        AstSymbol::Named("r", Box::new(AstSymbol::Location(AstLocation::End))),
+                    ],
+                    action: AstAction::Code("(l, r)")
+                }]
+            }]
+        }
+    }
+}
+
+#[test]
+fn extern_types() {
+    grammar_test! {
+        grammar {
+            token Token {}
+            grammar;
+
+            extern {
+                type Location = usize;
+                type Error = ();
+                type LexerError = LexError;
+            }
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: Vec::new(),
+                error_pattern: None
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl {
+                error_ty: Some(AstTypeRef::Tuple(Vec::new())),
+                location_ty: Some(AstTypeRef::simple_ty(vec!["usize"])),
+                lexer_error_ty: Some(AstTypeRef::simple_ty(vec!["LexError"]))
+            },
+            nonterminals: Vec::new()
+        }
+    };
+}
+
+#[test]
+fn error_token() {
+    grammar_test! {
+        grammar {
+            token Token {
+                "a" = Token::A,
+                error = Token::Error,
+            }
+            grammar;
+        },
+        AstGrammar {
+            imports: Vec::new(),
+            token_decl: AstTokenDecl {
+                ty: AstTypeRef::simple_ty(vec!["Token"]),
+                aliases: vec![AstTokenAlias {
+                    term: "\"a\"",
+                    pattern: AstTokenPattern::simple_path(vec!["Token", "A"])
+                }],
+                error_pattern: Some(AstTokenPattern::simple_path(vec!["Token", "Error"]))
+            },
+            grammar_decl: AstGrammarDecl::default(),
+            extern_decl: AstExternDecl::default(),
+            nonterminals: Vec::new()
+        }
+    };
+}
+
+#[test]
+fn action_code_literals() {
+    let grammar = r###"
+        use std::fmt::{self, Write};
+        token Token {}
+        grammar;
+        Start: () = {
+            => format!("a, b; {}", ')'),
+            => { let s: &'static str = r#"}", '"#; s },
+            => ('\'', b',', br"\", '\u{2C}'),
+        };
+    "###;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let actions: Vec<_> = actual_ast.nonterminals[0]
+        .productions
+        .iter()
+        .map(|production| production.action.clone())
+        .collect();
+
+    assert_eq!(actual_ast.imports, vec!["std::fmt::{self, Write}"]);
+    assert_eq!(
+        actions,
+        vec![
+            AstAction::Code(r#"format!("a, b; {}", ')')"#),
+            AstAction::Code(r##"{ let s: &'static str = r#"}", '"#; s }"##),
+            AstAction::Code(r#"('\'', b',', br"\", '\u{2C}')"#),
+        ]
+    );
+}
+
+#[test]
+fn identifiers_and_terminal_literals() {
+    let grammar = r####"
+        token Token {
+            "a\"b" = Token::Quoted,
+            r#"c\d"# = Token::Backslash,
+        }
+        grammar;
+        _Start_1: () = r#type "\x41" r"a""b" "c\\d";
+        r#type: () = Ünïcode;
+        Ünïcode: () = "A";
+    "####;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let terms: Vec<_> = actual_ast
+        .token_decl
+        .aliases
+        .iter()
+        .map(|alias| alias.term)
+        .collect();
+    let names: Vec<_> = actual_ast
+        .nonterminals
+        .iter()
+        .map(|nonterminal| nonterminal.name)
+        .collect();
+
+    assert_eq!(terms, vec![r#""a\"b""#, r#""c\\d""#]);
+    assert_eq!(names, vec!["_Start_1", "r#type", "Ünïcode"]);
+    assert_eq!(
+        actual_ast.nonterminals[0].productions[0].symbols,
+        vec![
+            AstSymbol::Nonterminal("r#type"),
+            AstSymbol::Terminal("\"A\""),
+            AstSymbol::Terminal("\"a\""),
+            AstSymbol::Terminal("\"b\""),
+            AstSymbol::Terminal(r#""c\\d""#),
+        ]
+    );
+}
+
+#[test]
+fn invalid_terminal_escape() {
+    let grammar = r#"
+        token Token {}
+        grammar;
+        Start: () = "\q";
+    "#;
+
+    assert!(crate::parsing::parse(grammar).is_err());
+}
+
+#[test]
+fn nonterminal_type_syntax() {
+    let grammar = r#"
+        token Token {}
+        grammar;
+        A: Box<dyn Fn(i32) -> i32 + Send> = "a";
+        B: <Vec<u8> as IntoIterator>::IntoIter = "a";
+        C: (&'a [u8], [u8; 4], (T), (T,)) = "a";
+        D: unsafe extern "C" fn(*const u8) -> ! = "a";
+        E: a::B<T>::C = "a";
+        F: impl Iterator<Item = &'static str> = "a";
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let tys: Vec<_> = actual_ast
+        .nonterminals
+        .iter()
+        .map(|nonterminal| nonterminal.ty.clone())
+        .collect();
+
+    let boxed = |ty| Box::new(ty);
+    let segment = |name, args| AstPathSegment { name, args };
+    let u8_ty = AstTypeRef::simple_ty(vec!["u8"]);
+    let t_ty = AstTypeRef::simple_ty(vec!["T"]);
+
+    assert_eq!(
+        tys,
+        vec![
+            AstTypeRef::Ty(
+                AstTypePath {
+                    is_absolute: false,
+                    segments: vec!["Box"]
+                },
+                vec![AstTypeRef::DynTrait(vec![
+                    AstTypeRef::Path {
+                        qself: None,
+                        is_absolute: false,
+                        segments: vec![segment(
+                            "Fn",
+                            AstGenericArgs::Parenthesized {
+                                inputs: vec![AstTypeRef::simple_ty(vec!["i32"])],
+                                output: Some(boxed(AstTypeRef::simple_ty(vec!["i32"])))
+                            }
+                        )]
+                    },
+                    AstTypeRef::simple_ty(vec!["Send"])
+                ])]
+            ),
+            AstTypeRef::Path {
+                qself: Some(AstQSelf {
+                    ty: boxed(AstTypeRef::Ty(
+                        AstTypePath {
+                            is_absolute: false,
+                            segments: vec!["Vec"]
+                        },
+                        vec![u8_ty.clone()]
+                    )),
+                    as_trait: Some(boxed(AstTypeRef::simple_ty(vec!["IntoIterator"])))
+                }),
+                is_absolute: false,
+                segments: vec![segment("IntoIter", AstGenericArgs::None)]
+            },
+            AstTypeRef::Tuple(vec![
+                AstTypeRef::Ref {
+                    lifetime: Some("'a"),
+                    is_mut: false,
+                    ty: boxed(AstTypeRef::Slice(boxed(u8_ty.clone())))
+                },
+                AstTypeRef::Array(boxed(u8_ty.clone()), "4"),
+                AstTypeRef::Paren(boxed(t_ty.clone())),
+                AstTypeRef::Tuple(vec![t_ty.clone()])
+            ]),
+            AstTypeRef::FnPtr {
+                is_unsafe: true,
+                abi: Some("\"C\""),
+                params: vec![AstTypeRef::Ptr {
+                    is_mut: false,
+                    ty: boxed(u8_ty)
+                }],
+                ret: Some(boxed(AstTypeRef::Never))
+            },
+            AstTypeRef::Path {
+                qself: None,
+                is_absolute: false,
+                segments: vec![
+                    segment("a", AstGenericArgs::None),
+                    segment("B", AstGenericArgs::AngleBracketed(vec![t_ty])),
+                    segment("C", AstGenericArgs::None)
+                ]
+            },
+            AstTypeRef::ImplTrait(vec![AstTypeRef::Ty(
+                AstTypePath {
+                    is_absolute: false,
+                    segments: vec!["Iterator"]
+                },
+                vec![AstTypeRef::AssocType(
+                    "Item",
+                    boxed(AstTypeRef::Ref {
+                        lifetime: Some("'static"),
+                        is_mut: false,
+                        ty: boxed(AstTypeRef::simple_ty(vec!["str"]))
+                    })
+                )]
+            )])
+        ]
+    );
+}
+
+#[test]
+fn attributes() {
+    let grammar = r#"
+        token Token {}
+        grammar;
+
+        /// Docs
+        #[cfg(feature = "a")]
+        # [doc = " More docs"]
+        pub Start: () = {
+            #[inline] #[label = "the letter a"] "a" => (),
+            #[allow(unused_variables)] "b",
+        };
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let nonterminal = &actual_ast.nonterminals[0];
+    let production_attrs: Vec<_> = nonterminal
+        .productions
+        .iter()
+        .map(|production| production.attrs.clone())
+        .collect();
+
+    assert_eq!(nonterminal.docs, vec![" Docs"]);
+    assert_eq!(
+        nonterminal.attrs,
+        vec![
+            AstAttribute {
+                name: "cfg",
+                code: "cfg(feature = \"a\")"
+            },
+            AstAttribute {
+                name: "doc",
+                code: "doc = \" More docs\""
+            }
+        ]
+    );
+    assert_eq!(
+        production_attrs,
+        vec![
+            vec![
+                AstAttribute::new("inline"),
+                AstAttribute::new("label = \"the letter a\"")
+            ],
+            vec![AstAttribute::new("allow(unused_variables)")]
+        ]
+    );
+}
+
+#[test]
+fn fold() {
+    let grammar = r#"
+        token Token {}
+        grammar;
+        Sum: i32 = fold(init: N, ("+" <N>) => |acc, (a, b)| acc + a.min(b));
+        Product: i32 = fold(N, N =>? |acc: i32, n| acc.checked_mul(n).ok_or(()));
+        Last: i32 = <fold:fold> => fold;
+        fold: i32 = <fold:N> => fold;
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let symbols: Vec<_> = actual_ast
+        .nonterminals
+        .iter()
+        .map(|nonterminal| nonterminal.productions[0].symbols.clone())
+        .collect();
+
+    assert_eq!(
+        symbols,
+        vec![
+            vec![AstSymbol::Fold(Box::new(AstFold {
+                init: AstSymbol::Nonterminal("N"),
+                step: AstSymbol::Group(vec![vec![
+                    AstSymbol::Terminal("\"+\""),
+                    AstSymbol::Selected(Box::new(AstSymbol::Nonterminal("N")))
+                ]]),
+                params: ("acc", "(a, b)"),
+                action: AstAction::Code("acc + a.min(b)")
+            }))],
+            vec![AstSymbol::Fold(Box::new(AstFold {
+                init: AstSymbol::Nonterminal("N"),
+                step: AstSymbol::Nonterminal("N"),
+                params: ("acc: i32", "n"),
+                action: AstAction::FallibleCode("acc.checked_mul(n).ok_or(())")
+            }))],
+            // `fold` is only a keyword when it starts a fold
+            vec![AstSymbol::Named(
+                "fold",
+                Box::new(AstSymbol::Nonterminal("fold"))
+            )],
+            vec![AstSymbol::Named(
+                "fold",
+                Box::new(AstSymbol::Nonterminal("N"))
+            )],
+        ]
+    );
+}
+
+#[test]
+fn invalid_fold() {
+    let invalid_grammars = [
+        "Sum: i32 = fold(start: N, N => |acc, n| acc + n);",
+        "Sum: i32 = fold(N, N => |acc| acc);",
+        "Sum: i32 = fold(N, N => acc);",
+    ];
+
+    for nonterminal in invalid_grammars {
+        let grammar = format!("token Token {{}} grammar; {}", nonterminal);
+        assert!(crate::parsing::parse(&grammar).is_err(), "{}", nonterminal);
+    }
+}
+
+#[test]
+fn mid_rule_actions() {
+    let grammar = r#"
+        token Token {
+            "a" = Token::A { value: _ },
+        }
+        grammar;
+        A: () = "a" { start(); } "a";
+        B: (u8, Vec<u8>) = {
+            { start() } "a" => (),
+            "a" <x:{ x() }: Vec<u8>> => x,
+        };
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let symbols: Vec<_> = actual_ast
+        .nonterminals
+        .iter()
+        .flat_map(|nonterminal| &nonterminal.productions)
+        .map(|production| production.symbols.clone())
+        .collect();
+    let vec_ty = AstTypeRef::Ty(
+        AstTypePath {
+            is_absolute: false,
+            segments: vec!["Vec"],
+        },
+        vec![AstTypeRef::simple_ty(vec!["u8"])],
+    );
+
+    assert_eq!(
+        symbols,
+        vec![
+            vec![
+                AstSymbol::Terminal("\"a\""),
+                AstSymbol::Action("start();", None),
+                AstSymbol::Terminal("\"a\"")
+            ],
+            vec![
+                AstSymbol::Action("start()", None),
+                AstSymbol::Terminal("\"a\"")
+            ],
+            vec![
+                AstSymbol::Terminal("\"a\""),
+                AstSymbol::Named("x", Box::new(AstSymbol::Action("x()", Some(vec_ty))))
+            ]
+        ]
+    );
+}
+
+#[test]
+fn nonterminal_inputs() {
+    let grammar = r#"
+        token Token {
+            "a" = Token::A { value: _ },
+        }
+        grammar;
+        A: () = <x:"a"> B(x, 1) "a" => ();
+        B(x: u8, y: Vec<u8>): () = "a";
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let vec_ty = AstTypeRef::Ty(
+        AstTypePath {
+            is_absolute: false,
+            segments: vec!["Vec"],
+        },
+        vec![AstTypeRef::simple_ty(vec!["u8"])],
+    );
+
+    assert_eq!(
+        actual_ast.nonterminals[0].productions[0].symbols,
+        vec![
+            AstSymbol::Named("x", Box::new(AstSymbol::Terminal("\"a\""))),
+            AstSymbol::Call("B", "x, 1"),
+            AstSymbol::Terminal("\"a\"")
+        ]
+    );
+    assert_eq!(actual_ast.nonterminals[0].inputs, vec![]);
+    assert_eq!(
+        actual_ast.nonterminals[1].inputs,
+        vec![
+            AstGrammarParam {
+                name: "x",
+                ty: AstTypeRef::simple_ty(vec!["u8"])
+            },
+            AstGrammarParam {
+                name: "y",
+                ty: vec_ty
+            }
+        ]
+    );
+}
+
+#[test]
+fn invalid_nonterminal_inputs() {
+    let lower = |nonterminals: &str, edit: fn(&mut AstGrammar)| {
+        let grammar = format!(
+            "token Token {{ \"a\" = Token::A }} grammar; {}",
+            nonterminals
+        );
+        let mut ast = crate::parsing::parse(&grammar).expect("Grammar should parse");
+        edit(&mut ast);
+        crate::ll_table_gen::lower_ebnf(&mut ast)
+            .and_then(|()| crate::ll_table_gen::lower_precedence(&mut ast))
+    };
+
+    assert_eq!(
+        lower("pub A(x: u8): () = \"a\";", |_| {}),
+        Err("A takes inputs, so it can't be public".to_string())
+    );
+    assert_eq!(
+        lower("A: () = B; B(x: u8): () = \"a\";", |_| {}),
+        Err("B takes inputs, so it has to be used with arguments".to_string())
+    );
+    assert_eq!(
+        lower(
+            "A: () = B(1); \
+             B(x: u8): () = { \"a\" => (), #[precedence(level = 1)] B(*x) \"a\" => () };",
+            |_| {}
+        ),
+        Err("B takes inputs, so it can't use precedence levels".to_string())
+    );
+    // Only the nonterminals declared with inputs are lexed as calls, so the
+    // inputs are removed after parsing
+    assert_eq!(
+        lower("A: () = B(1); B(x: u8): () = \"a\";", |ast| {
+            ast.nonterminals[1].inputs.clear()
+        }),
+        Err("B doesn't take inputs".to_string())
+    );
+}
+
+#[test]
+fn predicates() {
+    let grammar = r#"
+        token Token {
+            "a" = Token::A { value: _ },
+            "b" = Token::B,
+        }
+        grammar;
+        A: () = {
+            &{ is_a(token) } "a" => (),
+            &("a" "b") &{ true } "a" "b" => (),
+        };
+    "#;
+    let actual_ast = crate::parsing::parse(grammar).expect("Grammar should parse");
+    let predicates: Vec<_> = actual_ast.nonterminals[0]
+        .productions
+        .iter()
+        .map(|production| production.predicates.clone())
+        .collect();
+
+    assert_eq!(
+        predicates,
+        vec![
+            vec![AstPredicate::Semantic("is_a(token)")],
+            vec![
+                AstPredicate::Syntactic(vec!["\"a\"", "\"b\""]),
+                AstPredicate::Semantic("true")
+            ]
+        ]
+    );
+}
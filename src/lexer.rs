@@ -1,16 +1,42 @@
-use logos::{Lexer, Logos};
+use crate::ll_table_gen::intern;
+use logos::{Filter, Lexer, Logos};
+use std::collections::HashSet;
 use std::ops::Range;
 
 #[derive(Logos, Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Token<'input> {
     #[token("grammar")]
     Grammar,
+    /// `grammar<` would otherwise be lexed as a macro identifier
+    #[token("grammar<")]
+    GrammarLAngle,
     #[token("token")]
     Token,
     #[token("use")]
     Use,
     #[token("pub")]
     Pub,
+    #[token("where")]
+    Where,
+    #[token("mut")]
+    Mut,
+    #[token("extern")]
+    Extern,
+    #[token("type")]
+    Type,
+    #[token("as")]
+    As,
+    #[token("dyn")]
+    Dyn,
+    #[token("impl")]
+    Impl,
+    #[token("fn")]
+    Fn,
+    #[token("const")]
+    Const,
+    #[token("unsafe")]
+    Unsafe,
     #[token(";")]
     Semicolon,
     #[token(":")]
@@ -23,34 +49,109 @@ pub enum Token<'input> {
     RBrace,
     #[token("=>")]
     EqArrow,
+    #[token("=>?")]
+    EqArrowQuestion,
     #[token("=")]
     Equal,
+    #[token("->")]
+    Arrow,
     #[token(",")]
     Comma,
+    #[token("|")]
+    Pipe,
+    #[token("_")]
+    Underscore,
+    #[token("..")]
+    DotDot,
+    #[token("*")]
+    Star,
+    #[token("+")]
+    Plus,
+    #[token("?")]
+    Question,
+    #[token("!")]
+    Bang,
     #[token(")")]
     RParen,
     #[token("(")]
     LParen,
+    #[token("]")]
+    RBracket,
+    #[token("[")]
+    LBracket,
     #[token("<")]
     LAngle,
     #[token(">")]
     RAngle,
+    #[token("&")]
+    Ampersand,
+    #[token("@")]
+    At,
 
-    /// Identifiers are alphanumeric names
-    #[regex("[a-zA-Z][a-zA-Z0-9]*")]
+    /// Identifiers follow Rust's rules, including raw identifiers (`r#type`)
+    #[regex(r"(r#)?[_\p{XID_Start}]\p{XID_Continue}*")]
     Identifier(&'input str),
 
-    /// Terminals are anything enclosed in double quotes
-    #[regex("\"[^\"]+\"")]
+    /// An identifier immediately followed by `<`, which starts the arguments
+    /// of a macro or generic type. This avoids ambiguity with a following
+    /// named symbol (`A <b:B>`).
+    #[regex(r"(r#)?[_\p{XID_Start}]\p{XID_Continue}*<", |lex| lex.slice().trim_end_matches('<'))]
+    MacroIdentifier(&'input str),
+
+    /// Terminals are Rust string literals, which may be raw or contain escapes.
+    /// They're canonicalized (to the `Debug` format of their text), so the same
+    /// terminal can be written in different ways.
+    #[regex(r#""([^"\\]|\\(.|\n))*""#, parse_terminal)]
+    #[regex(r#"r#*""#, parse_raw_terminal)]
     Terminal(&'input str),
 
-    // These two tokens are generated by the sub-lexers ImportToken and ActionToken
+    /// Lifetimes are used in types and generic parameters
+    #[regex("'[a-zA-Z_][a-zA-Z0-9_]*")]
+    Lifetime(&'input str),
+
+    /// Char literals are used in token patterns
+    #[regex(r"'(\\.[^']*|[^\\'])'")]
+    Char(&'input str),
+
+    /// Number literals are used in token patterns
+    #[regex("[0-9][0-9a-zA-Z_]*")]
+    Number(&'input str),
+
+    /// Doc comments are kept (without the `///`) so they can be attached to
+    /// nonterminals and productions
+    #[regex("///[^\n]*", |lex| &lex.slice()[3..])]
+    DocComment(&'input str),
+
+    /// The start of an attribute. Doc comments in the macro input also become
+    /// attributes (`# [doc = "..."]`), which are spaced out like this.
+    #[regex(r"#[ \t\n\f]*\[")]
+    AttributeStart,
+
+    // These tokens are generated by the sub-lexers ImportToken, ActionToken
+    // and AttributeToken
     ImportCode(&'input str),
     ActionCode(&'input str),
+    AttributeCode(&'input str),
+
+    /// The opening brace and code of a mid-rule action (`{ code }`). This is
+    /// generated by the StatefulLexer from a `{` between symbols.
+    MidRuleCode(&'input str),
+
+    /// A use of a nonterminal with inputs, including its arguments
+    /// (`Declarator(ty.clone())`). This is generated by the StatefulLexer, as
+    /// it would otherwise be a nonterminal followed by a group.
+    NonterminalCall(&'input str),
+
+    /// The `fold` keyword, which is only one when it starts a fold
+    /// (`fold(...)`) among the symbols of a production. Elsewhere it's an
+    /// identifier. This is generated by the StatefulLexer.
+    Fold,
 
     #[error]
-    // Skip whitespace
+    // Skip whitespace and comments
     #[regex(r"[ \t\n\f]+", logos::skip)]
+    #[regex("//[^\n]*", logos::skip)]
+    #[regex(r"/\*", skip_block_comment)]
     Error,
 }
 
@@ -58,12 +159,14 @@ pub enum Token<'input> {
 enum ImportToken<'input> {
     /// This token represents a Rust import expression (everything after "use"
     /// and before ";").
-    #[regex(r"[^ \t\n\f][^;]*")]
+    #[regex(r".", parse_import_code)]
     ImportCode(&'input str),
 
     #[error]
-    // Skip whitespace
-    #[regex(r"[ \t\n\f]+", logos::skip)]
+    // Skip whitespace and comments
+    #[regex(r"[ \t\n\f]+", logos::skip, priority = 2)]
+    #[regex("//[^\n]*", logos::skip)]
+    #[regex(r"/\*", skip_block_comment)]
     Error,
 }
 
@@ -75,23 +178,221 @@ enum ActionToken<'input> {
     ActionCode(&'input str),
 
     #[error]
-    // Skip whitespace
+    // Skip whitespace and comments
+    #[regex(r"[ \t\n\f]+", logos::skip, priority = 2)]
+    #[regex("//[^\n]*", logos::skip)]
+    #[regex(r"/\*", skip_block_comment)]
+    Error,
+}
+
+#[derive(Logos)]
+enum AttributeToken<'input> {
+    /// This token represents the contents of an attribute (everything between
+    /// "#[" and "]").
+    #[regex(r".", parse_attribute_code)]
+    AttributeCode(&'input str),
+
+    #[error]
+    // Skip whitespace and comments
     #[regex(r"[ \t\n\f]+", logos::skip, priority = 2)]
+    #[regex("//[^\n]*", logos::skip)]
+    #[regex(r"/\*", skip_block_comment)]
     Error,
 }
 
+/// Skip a block comment, which may contain nested block comments. An
+/// unterminated comment is emitted as an error.
+fn skip_block_comment<'input, T>(lexer: &mut Lexer<'input, T>) -> Filter<()>
+where
+    T: Logos<'input, Source = str>,
+{
+    let comment = &lexer.source()[lexer.span().start..];
+
+    match block_comment_len(comment) {
+        Some(len) => {
+            lexer.bump(len - lexer.slice().len());
+            Filter::Skip
+        }
+        None => {
+            lexer.bump(comment.len() - lexer.slice().len());
+            Filter::Emit(())
+        }
+    }
+}
+
+/// Get the length of the block comment at the start of `source`, or `None` if
+/// it isn't terminated.
+fn block_comment_len(source: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < source.len() {
+        let rest = &source[i..];
+
+        if rest.starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            i += 2;
+
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+
+    None
+}
+
+fn parse_terminal<'input>(lexer: &mut Lexer<'input, Token<'input>>) -> Option<&'input str> {
+    let literal = lexer.slice();
+    let text = unescape(&literal[1..(literal.len() - 1)])?;
+    Some(canonical_terminal(literal, &text))
+}
+
+fn parse_raw_terminal<'input>(lexer: &mut Lexer<'input, Token<'input>>) -> Option<&'input str> {
+    let hashes = lexer.slice().len() - 2;
+    let literal = &lexer.source()[(lexer.span().end - 1)..];
+    let len = string_len(literal, Some(hashes));
+    lexer.bump(len - 1);
+
+    // An unterminated string takes up the rest of the source, and so doesn't
+    // end with the closing quote (unless it's the opening one)
+    let literal = &literal[..len];
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    let text = literal
+        .strip_suffix(&terminator)
+        .filter(|_| literal.len() > terminator.len())?;
+    Some(canonical_terminal(lexer.slice(), &text[1..]))
+}
+
+/// Get the canonical form of a terminal with the given text, which borrows the
+/// `literal` it was written as if that's already canonical
+fn canonical_terminal<'input>(literal: &'input str, text: &str) -> &'input str {
+    let canonical = format!("{:?}", text);
+    if canonical == literal {
+        literal
+    } else {
+        intern(canonical)
+    }
+}
+
+/// Replace the escapes in the contents of a (non-raw) string literal, or return
+/// `None` if any of them are invalid
+pub(crate) fn unescape(literal: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut chars = literal.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => text.push('\n'),
+            'r' => text.push('\r'),
+            't' => text.push('\t'),
+            '0' => text.push('\0'),
+            c @ ('\\' | '\'' | '"') => text.push(c),
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                let value = u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|&value| value <= 0x7F)?;
+                text.push(char::from(value));
+            }
+            'u' => {
+                if chars.next()? != '{' {
+                    return None;
+                }
+                let digits: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let value = u32::from_str_radix(&digits.replace('_', ""), 16).ok()?;
+                text.push(char::from_u32(value)?);
+            }
+            // A line continuation skips the newline and any leading whitespace
+            '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            _ => return None,
+        }
+    }
+
+    Some(text)
+}
+
 fn parse_action_code<'input>(lexer: &mut Lexer<'input, ActionToken<'input>>) -> &'input str {
-    // FIXME: This lexing fails in multiple ways because it doesn't handle
-    //        strings, comments, and other elements of Rust code that could
-    //        contain extra parens or comma/semicolons. Try replicating
-    //        LALRPOP's lexing behavior:
-    //        https://github.com/lalrpop/lalrpop/blob/fc9986c725d908a60b11d8480711afa33f7f3564/lalrpop/src/tok/mod.rs#L433
+    let code = &lexer.source()[lexer.span().start..];
+    // The parameters of a closure (in a fold) can contain commas
+    let params_len = closure_params_len(code).unwrap_or(0);
+    let len = params_len + rust_code_len(&code[params_len..], &[',', ';']);
+
+    // The first char is already in the slice
+    lexer.bump(len.saturating_sub(lexer.slice().len()));
+    lexer.slice()
+}
+
+/// Get the length of the parameters (`|acc, rhs|`) at the start of a closure
+fn closure_params_len(code: &str) -> Option<usize> {
+    let params = code.strip_prefix('|')?;
+    let len = rust_code_len(params, &['|']);
+
+    params[len..].starts_with('|').then(|| len + 2)
+}
+
+/// Split a closure (`|acc, rhs| acc + rhs`) into its parameters and body
+pub(crate) fn split_closure(code: &str) -> Option<(Vec<&str>, &str)> {
+    let params_len = closure_params_len(code)?;
+    let mut params_code = &code[1..(params_len - 1)];
+    let mut params = Vec::new();
+
+    while !params_code.trim().is_empty() {
+        let len = rust_code_len(params_code, &[',']);
+        params.push(params_code[..len].trim());
+        params_code = params_code[len..].strip_prefix(',').unwrap_or("");
+    }
+
+    Some((params, code[params_len..].trim()))
+}
+
+fn parse_import_code<'input>(lexer: &mut Lexer<'input, ImportToken<'input>>) -> &'input str {
+    let code = &lexer.source()[lexer.span().start..];
+    let len = rust_code_len(code, &[';']);
+
+    // The first char is already in the slice
+    lexer.bump(len.saturating_sub(lexer.slice().len()));
+    lexer.slice()
+}
+
+fn parse_attribute_code<'input>(lexer: &mut Lexer<'input, AttributeToken<'input>>) -> &'input str {
+    let code = &lexer.source()[lexer.span().start..];
+    let len = rust_code_len(code, &[]);
+
+    // The first char is already in the slice
+    lexer.bump(len.saturating_sub(lexer.slice().len()));
+    lexer.slice()
+}
+
+/// Get the length of the Rust code at the start of `code`. The code ends before
+/// an unbalanced closing bracket, or one of the `terminators` outside of any
+/// brackets. Literals, lifetimes and comments are skipped over as whole tokens,
+/// so the brackets and terminators inside of them don't count.
+fn rust_code_len(code: &str, terminators: &[char]) -> usize {
     let mut balance = 0;
-    let chars = lexer.slice().chars().chain(lexer.remainder().chars());
+    let mut i = 0;
+
+    while let Some(c) = code[i..].chars().next() {
+        if let Some(len) = opaque_token_len(&code[i..]) {
+            i += len;
+            continue;
+        }
 
-    for (i, c) in chars.enumerate() {
         match c {
-            '(' | '[' | '{' => balance += 1,
+            '(' | '[' | '{' => {
+                balance += 1;
+                i += 1;
+            }
             ')' | ']' | '}' => {
                 // Check if we're expecting a closing brace. If not, then we're
                 // done with the Rust code
@@ -99,23 +400,138 @@ fn parse_action_code<'input>(lexer: &mut Lexer<'input, ActionToken<'input>>) ->
                     break;
                 }
 
-                balance -= 1
+                balance -= 1;
+                i += 1;
             }
-            ';' | ',' => {
-                if balance == 0 {
-                    break;
-                }
+            c if balance == 0 && terminators.contains(&c) => break,
+            c => i += c.len_utf8(),
+        }
+    }
+
+    i
+}
+
+/// Find the `<>` placeholders in action code, which stand for the selected
+/// values. The ones in literals and comments are left alone.
+pub(crate) fn find_placeholders(code: &str) -> Vec<usize> {
+    let mut placeholders = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = code[i..].chars().next() {
+        if let Some(len) = opaque_token_len(&code[i..]) {
+            i += len;
+        } else if code[i..].starts_with("<>") {
+            placeholders.push(i);
+            i += 2;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+
+    placeholders
+}
+
+/// Replace the `<>` placeholders in action code with `value`
+pub(crate) fn replace_placeholders(code: &str, value: &str) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+
+    for i in find_placeholders(code) {
+        result.push_str(&code[last..i]);
+        result.push_str(value);
+        last = i + 2;
+    }
+    result.push_str(&code[last..]);
+
+    result
+}
+
+/// Get the length of the comment, literal, lifetime or identifier at the start
+/// of `source`, which are skipped over as whole tokens when scanning Rust code
+fn opaque_token_len(source: &str) -> Option<usize> {
+    if source.starts_with("//") {
+        return Some(source.find('\n').unwrap_or(source.len()));
+    }
+    if source.starts_with("/*") {
+        return Some(block_comment_len(source).unwrap_or(source.len()));
+    }
+
+    match source.chars().next()? {
+        '"' => Some(string_len(source, None)),
+        '\'' => Some(char_or_lifetime_len(source)),
+        // Identifiers are skipped whole so their prefixes (`r`, `b`, `br`)
+        // can be matched with raw/byte literals
+        c if c.is_alphabetic() || c == '_' => {
+            let ident_len = source
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(source.len());
+            let after_ident = &source[ident_len..];
+            let hashes = after_ident.len() - after_ident.trim_start_matches('#').len();
+
+            Some(
+                match (&source[..ident_len], after_ident[hashes..].chars().next()) {
+                    ("r" | "br", Some('"')) => {
+                        ident_len + hashes + string_len(&after_ident[hashes..], Some(hashes))
+                    }
+                    ("b", Some('"')) if hashes == 0 => ident_len + string_len(after_ident, None),
+                    ("b", Some('\'')) if hashes == 0 => {
+                        ident_len + char_or_lifetime_len(after_ident)
+                    }
+                    _ => ident_len,
+                },
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Get the length of the string literal at the start of `source`, after any
+/// prefix. Raw strings don't have escapes, and end with a quote followed by the
+/// same number of hashes (`raw_hashes`) as they started with. Unterminated
+/// strings take up the rest of the source.
+fn string_len(source: &str, raw_hashes: Option<usize>) -> usize {
+    let terminator = format!("\"{}", "#".repeat(raw_hashes.unwrap_or(0)));
+    let mut chars = source.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if raw_hashes.is_none() => {
+                chars.next();
             }
+            '"' if source[i..].starts_with(&terminator) => return i + terminator.len(),
             _ => {}
         }
+    }
 
-        if i != 0 {
-            // Don't bump the first char because it's already in the slice
-            lexer.bump(c.len_utf8());
+    source.len()
+}
+
+/// Get the length of the char literal (`'a'`, `'\n'`) or lifetime (`'a`) at the
+/// start of `source`
+fn char_or_lifetime_len(source: &str) -> usize {
+    let mut chars = source.char_indices().skip(1);
+
+    match chars.next() {
+        // Escaped char literal
+        Some((_, '\\')) => {
+            chars.next();
+            chars
+                .find(|(_, c)| *c == '\'')
+                .map_or(source.len(), |(i, _)| i + 1)
         }
+        Some((_, c)) => match chars.next() {
+            Some((i, '\'')) => i + 1,
+            // A lifetime or label
+            _ if c.is_alphabetic() || c == '_' => {
+                let name = &source[1..];
+                1 + name
+                    .find(|c: char| !c.is_alphanumeric() && c != '_')
+                    .unwrap_or(name.len())
+            }
+            _ => 1 + c.len_utf8(),
+        },
+        None => 1,
     }
-
-    lexer.slice()
 }
 
 /// Wrap the lexer with some state so it can switch between lexer
@@ -123,21 +539,82 @@ fn parse_action_code<'input>(lexer: &mut Lexer<'input, ActionToken<'input>>) ->
 /// parsing Rust code, skipping over it in imports and action code blocks.
 pub struct StatefulLexer<'input> {
     state: LexerState<'input>,
+    /// How many braces are open, outside of the Rust code
+    brace_depth: usize,
+    /// Whether the tokens are in a nonterminal's productions (after `=` at the
+    /// top level), where braces start mid-rule actions
+    in_productions: bool,
+    /// Whether the last token was `=`, so a brace starts a list of productions
+    after_equal: bool,
+    /// The nonterminals declared with inputs, which are followed by their
+    /// arguments where they're used
+    input_nonterminals: HashSet<&'input str>,
 }
 
 enum LexerState<'input> {
     Normal(Lexer<'input, Token<'input>>),
     Import(Lexer<'input, ImportToken<'input>>),
     ActionCode(Lexer<'input, ActionToken<'input>>),
+    Attribute(Lexer<'input, AttributeToken<'input>>),
     Done,
 }
 
 impl<'input> StatefulLexer<'input> {
     pub fn new(lexer: Lexer<'input, Token<'input>>) -> Self {
+        let input_nonterminals =
+            Self::without_calls(Token::lexer(lexer.source())).scan_input_nonterminals();
+
+        Self {
+            input_nonterminals,
+            ..Self::without_calls(lexer)
+        }
+    }
+
+    fn without_calls(lexer: Lexer<'input, Token<'input>>) -> Self {
         Self {
             state: LexerState::Normal(lexer),
+            brace_depth: 0,
+            in_productions: false,
+            after_equal: false,
+            input_nonterminals: HashSet::new(),
         }
     }
+
+    /// Find the nonterminals which are declared with inputs
+    /// (`Declarator(base: Type): Decl = ...`)
+    fn scan_input_nonterminals(mut self) -> HashSet<&'input str> {
+        let mut names = HashSet::new();
+        // The last two tokens, to find a name followed by `(` at the start
+        // of a nonterminal
+        let mut last_tokens = (None, None);
+
+        while let Some(item) = self.next() {
+            let token = item.ok().map(|(_, token, _)| token);
+
+            match (&last_tokens, &token) {
+                ((Some(before), Some(Token::Identifier(name))), Some(Token::LParen))
+                    if self.brace_depth == 0 && !self.in_productions =>
+                {
+                    let starts_nonterminal = matches!(
+                        before,
+                        Token::Semicolon
+                            | Token::Pub
+                            | Token::RBracket
+                            | Token::RBrace
+                            | Token::DocComment(_)
+                    );
+                    if starts_nonterminal {
+                        names.insert(*name);
+                    }
+                }
+                _ => {}
+            }
+
+            last_tokens = (last_tokens.1, token);
+        }
+
+        names
+    }
 }
 
 impl<'input> Iterator for StatefulLexer<'input> {
@@ -146,12 +623,46 @@ impl<'input> Iterator for StatefulLexer<'input> {
     fn next(&mut self) -> Option<Self::Item> {
         match std::mem::replace(&mut self.state, LexerState::Done) {
             LexerState::Normal(mut lexer) => {
-                let token = lexer.next()?;
+                let mut token = lexer.next()?;
+                let is_mid_rule =
+                    matches!(token, Token::LBrace) && self.in_productions && !self.after_equal;
+                if is_mid_rule {
+                    let len = rust_code_len(lexer.remainder(), &[]);
+                    lexer.bump(len);
+                    token = Token::MidRuleCode(&lexer.slice()[1..]);
+                }
+                if let Token::Identifier(name) = token {
+                    let args = lexer.remainder().trim_start();
+                    let is_call = self.in_productions
+                        && self.input_nonterminals.contains(name)
+                        && args.starts_with('(');
+                    if is_call {
+                        let args_len = 1 + rust_code_len(&args[1..], &[]);
+                        let close_len = usize::from(args[args_len..].starts_with(')'));
+                        let space_len = lexer.remainder().len() - args.len();
+                        lexer.bump(space_len + args_len + close_len);
+                        token = Token::NonterminalCall(lexer.slice());
+                    } else if name == "fold" && self.in_productions && args.starts_with('(') {
+                        token = Token::Fold;
+                    }
+                }
                 let span = lexer.span();
 
+                match token {
+                    Token::LBrace | Token::MidRuleCode(_) => self.brace_depth += 1,
+                    Token::RBrace => self.brace_depth = self.brace_depth.saturating_sub(1),
+                    Token::Equal if self.brace_depth == 0 => self.in_productions = true,
+                    Token::Semicolon if self.brace_depth == 0 => self.in_productions = false,
+                    _ => {}
+                }
+                self.after_equal = matches!(token, Token::Equal);
+
                 self.state = match token {
                     Token::Use => LexerState::Import(lexer.morph()),
-                    Token::EqArrow => LexerState::ActionCode(lexer.morph()),
+                    Token::EqArrow | Token::EqArrowQuestion => {
+                        LexerState::ActionCode(lexer.morph())
+                    }
+                    Token::AttributeStart => LexerState::Attribute(lexer.morph()),
                     _ => LexerState::Normal(lexer),
                 };
 
@@ -182,6 +693,17 @@ impl<'input> Iterator for StatefulLexer<'input> {
                     ActionToken::Error => Err(span),
                 })
             }
+            LexerState::Attribute(mut lexer) => {
+                let token = lexer.next()?;
+                let span = lexer.span();
+                self.state = LexerState::Normal(lexer.morph());
+                Some(match token {
+                    AttributeToken::AttributeCode(code) => {
+                        Ok((span.start, Token::AttributeCode(code), span.end))
+                    }
+                    AttributeToken::Error => Err(span),
+                })
+            }
             LexerState::Done => None,
         }
     }
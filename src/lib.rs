@@ -1,16 +1,22 @@
 #[macro_use]
 extern crate lalrpop_util;
 
-use crate::ast::{AstGrammar, AstProduction, AstSymbol, AstTypePath, AstTypeRef};
+use crate::ast::{
+    AstAction, AstAttribute, AstFieldPattern, AstGenericArgs, AstGenericParam, AstGrammar,
+    AstGrammarParam, AstLocation, AstNonterminal, AstPathSegment, AstPredicate, AstProduction,
+    AstQSelf, AstSymbol, AstTokenPattern, AstTypePath, AstTypeRef, AstWherePredicate,
+};
+use crate::lexer::{replace_placeholders, unescape};
 use crate::ll_table_gen::{
-    compute_first, compute_follow, compute_nullable, compute_parse_table,
-    insert_wrapper_start_nonterm, FirstMap, ParseTable, EOF_TERMINAL,
+    check_token_patterns, compute_first, compute_follow, compute_nullable, compute_parse_table,
+    find_conflict, insert_wrapper_start_nonterms, lower_ebnf, lower_precedence, resolve_conflicts,
+    FirstMap, ParseTable, EOF_TERMINAL,
 };
 use crate::parsing::parse;
 use proc_macro::TokenStream;
-use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use proc_macro2::{Ident, TokenStream as TokenStream2};
 use quote::{format_ident, quote, ToTokens};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 #[cfg(test)]
@@ -23,7 +29,7 @@ mod ll_table_gen;
 mod parsing;
 
 type NameMap<'input> = HashMap<&'input str, Ident>;
-type NontermTyMap<'input> = HashMap<&'input str, &'input AstTypeRef<'input>>;
+type SymbolTyMap<'input> = HashMap<&'input str, AstTypeRef<'input>>;
 type TokenPatMap<'input> = HashMap<&'input str, TokenStream2>;
 /// Map from nonterminal name and production to production ID
 type ProductionIdMap<'input> = HashMap<(&'input str, &'input AstProduction<'input>), usize>;
@@ -32,44 +38,53 @@ type ProductionIdMap<'input> = HashMap<(&'input str, &'input AstProduction<'inpu
 pub fn ll_parser(input: TokenStream) -> TokenStream {
     let input = input.to_string();
     let mut ast = parse(&input).unwrap(); // TODO: return error
-    insert_wrapper_start_nonterm(&mut ast);
+    if let Err(message) = check_token_patterns(&ast)
+        .and_then(|()| lower_ebnf(&mut ast))
+        .and_then(|()| lower_precedence(&mut ast))
+    {
+        return compile_error(&message);
+    }
+    insert_wrapper_start_nonterms(&mut ast);
 
     // Compute LL(1) parse table
     let nullable = compute_nullable(&ast);
     let first = compute_first(&ast, &nullable);
     let follow = compute_follow(&ast, &nullable, &first);
-    let parse_table = compute_parse_table(&ast, &nullable, &first, &follow);
+    let mut parse_table = compute_parse_table(&ast, &nullable, &first, &follow);
+    let resolution_notes = resolve_conflicts(&ast, &mut parse_table, &nullable, &first);
 
     // Check for parse table conflicts
-    if let Some(((symbol, terminal), productions)) = parse_table
-        .iter()
-        .find(|(_, productions)| productions.len() > 1)
-    {
+    if let Some(((symbol, terminal), productions)) = find_conflict(&parse_table) {
         let message = format!(
-            "Found a parse-table conflict at symbol \"{}\" and terminal {}:\n{:#?}",
+            "Found a parse-table conflict at symbol \"{}\" and terminal {}. Mark the \
+             production to choose with #[prefer], or the nonterminal with #[resolve(shift)]:\n{:#?}",
             symbol, terminal, productions
         );
-
-        let result = quote! {
-            compile_error!(#message);
-        };
-        return result.into();
+        return compile_error(&message);
     }
 
     // Compute some info about names, nonterminals, etc upfront.
     let name_map = generate_name_map(&ast);
-    let nonterm_ty_map = generate_nonterm_ty_map(&ast);
+    let symbol_ty_map = generate_symbol_ty_map(&ast);
     let token_pats = generate_token_pat_map(&ast);
     let production_ids = generate_production_id_map(&ast);
+    let generics = generate_generics(&ast);
+    let grammar_params = generate_grammar_params(&ast);
 
     // Create data structures and functions
-    let action_result_enum = generate_action_result_enum(&ast, &name_map);
+    let action_result_enum =
+        generate_action_result_enum(&ast, &name_map, &symbol_ty_map, &generics);
     let symbols_enum = generate_symbol_enum(&ast, &name_map);
     let symbol_impl = generate_symbol_impl(&ast, &name_map);
     let symbol_eq_impl = generate_partial_eq_impl(&ast, &name_map, &token_pats);
-    let action_result_pop_fns = generate_action_result_pop_fns(&ast, &name_map);
-    let action_fns = generate_action_fns(&ast, &name_map, &nonterm_ty_map);
-    let reduce_fns = generate_reduce_fns(&ast, &name_map);
+    let token_value_fn = generate_token_value_fn(&ast, &name_map, &generics);
+    let action_result_pop_fns =
+        generate_action_result_pop_fns(&ast, &name_map, &symbol_ty_map, &generics);
+    let repetition_fns = generate_repetition_fns(&ast, &generics, &grammar_params);
+    let action_fns =
+        generate_action_fns(&ast, &name_map, &symbol_ty_map, &generics, &grammar_params);
+    let reduce_fns = generate_reduce_fns(&ast, &name_map, &generics, &grammar_params);
+    let inherit_fns = generate_inherit_fns(&ast, &name_map, &generics, &grammar_params);
     let parse_fn = generate_parse_fn(
         &ast,
         &name_map,
@@ -77,55 +92,107 @@ pub fn ll_parser(input: TokenStream) -> TokenStream {
         &production_ids,
         &parse_table,
         &first,
+        &generics,
+        &grammar_params,
     );
 
     // Generate output code
     let imports: Vec<_> = ast
         .imports
-        .into_iter()
-        .map(TokenStream2::from_str)
+        .iter()
+        .map(|import| TokenStream2::from_str(import))
         .collect::<Result<_, _>>()
         .unwrap();
+    let Generics {
+        params,
+        args,
+        where_clause,
+        ..
+    } = &generics;
+    let param_tys = &grammar_params.tys;
+    let error_ty = generate_error_ty(&ast);
+    let location_ty = ast.location_ty();
+    let parse_error_enum = generate_parse_error_enum(&ast);
+    let resolution_warnings = generate_resolution_warnings(&resolution_notes);
     let output = quote! {
         // TODO: allow for user-specified module name
         #(use #imports;)*
 
-        enum SymbolOrReduction {
+        enum SymbolOrReduction #params #where_clause {
             Symbol(Symbol),
-            Reduction(fn(&mut std::vec::Vec<ActionResult>)),
+            Reduction(
+                fn(
+                    &mut std::vec::Vec<(#location_ty, ActionResult #args, #location_ty)>,
+                    &mut std::vec::Vec<ActionResult #args>
+                    #(, #param_tys)*
+                ) -> Result<(), #error_ty>
+            ),
         }
 
         #action_result_enum
 
-        #[derive(Debug, PartialEq)]
-        pub enum ParseError<T> {
-            UnexpectedEOF,
-            ExtraToken(T),
-            UnrecognizedToken {
-                expected: std::vec::Vec<&'static str>,
-                found: T,
-            },
+        /// Get the end of the last parsed symbol, which is where empty
+        /// productions are located
+        fn last_location #params (
+            results: &[(#location_ty, ActionResult #args, #location_ty)],
+        ) -> #location_ty #where_clause {
+            results
+                .last()
+                .map_or_else(Default::default, |(_, _, end)| end.clone())
         }
 
+        #parse_error_enum
+
         #symbols_enum
         #symbol_impl
         #symbol_eq_impl
 
-        impl PartialEq<Symbol> for Token {
-            fn eq(&self, other: &Symbol) -> bool {
-                other.eq(self)
-            }
-        }
-
+        #token_value_fn
         #action_result_pop_fns
+        #repetition_fns
         #action_fns
         #reduce_fns
+        #inherit_fns
 
         #parse_fn
+
+        #resolution_warnings
     };
     output.into()
 }
 
+/// Report an error in the grammar where the macro is used
+fn compile_error(message: &str) -> TokenStream {
+    let result = quote! {
+        compile_error!(#message);
+    };
+    result.into()
+}
+
+/// Generate a warning for each resolved parse-table conflict, so they aren't
+/// hidden. Proc macros can't emit warnings directly on stable, so each note is
+/// attached to a deprecated item which is then used.
+fn generate_resolution_warnings(notes: &[String]) -> TokenStream2 {
+    notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| {
+            let name = format_ident!("ll_parser_resolved_conflict_{}", i);
+
+            // Using a deprecated item is a warning which shows its note, so
+            // the const is used right after it's declared. It's still a lint,
+            // so users can silence it with `#[allow(deprecated)]`.
+            quote! {
+                #[deprecated(note = #note)]
+                #[allow(non_upper_case_globals)]
+                const #name: () = ();
+                const _: () = #name;
+            }
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
 fn generate_parse_fn<'a>(
     ast: &AstGrammar,
     names: &NameMap,
@@ -133,22 +200,193 @@ fn generate_parse_fn<'a>(
     production_ids: &ProductionIdMap<'a>,
     parse_table: &'a ParseTable<'a>,
     first_map: &FirstMap,
+    generics: &Generics,
+    grammar_params: &GrammarParams,
 ) -> TokenStream2 {
-    let token_ty = Ident::new(ast.token_decl.name, Span::call_site());
-    let start_nonterm = ast
-        .nonterminals
-        .iter()
-        .find(|nonterminal| nonterminal.is_pub)
-        .expect("Must have a single public nonterminal");
-    let return_ty = &start_nonterm.ty;
-    let start_nonterm_canonical = &names[start_nonterm.name];
-    let return_pop_fn = format_ident!("pop_{}", start_nonterm_canonical);
+    let token_ty = &ast.token_decl.ty;
+    let end_variant = &names[EOF_TERMINAL];
+    let Generics {
+        params,
+        args,
+        turbofish,
+        where_clause,
+    } = generics;
+    let GrammarParams {
+        names: param_names,
+        tys: param_tys,
+    } = grammar_params;
+    let error_ty = generate_error_ty(ast);
+    let location_ty = ast.location_ty();
+
+    let is_spanned = ast.extern_decl.location_ty.is_some();
+    let item_ty = if is_spanned {
+        quote! { (#location_ty, #token_ty, #location_ty) }
+    } else {
+        quote! { #token_ty }
+    };
+
+    // Fallible lexers yield `Result`s, and their errors end the parse
+    let (lexer_item_ty, next_raw_item, peek_raw_item, lexer_error_arg) =
+        match &ast.extern_decl.lexer_error_ty {
+            Some(lexer_error_ty) => {
+                let lexer_error = if is_spanned {
+                    quote! { ParseError::Lexer { error, location: lookbehind.clone() } }
+                } else {
+                    quote! { ParseError::Lexer(error) }
+                };
+
+                (
+                    quote! { Result<#item_ty, #lexer_error_ty> },
+                    quote! { lexer.next().transpose().map_err(|error| #lexer_error)? },
+                    quote! {
+                        match lexer.peek_nth(0) {
+                            Some(Err(_)) => {
+                                let error = lexer.next().and_then(Result::err).unwrap();
+                                return Err(#lexer_error);
+                            }
+                            Some(Ok(item)) => Some(item),
+                            None => None,
+                        }
+                    },
+                    Some(quote! { , #lexer_error_ty }),
+                )
+            }
+            None => (
+                item_ty.clone(),
+                quote! { lexer.next() },
+                quote! { lexer.peek_nth(0) },
+                None,
+            ),
+        };
+
+    // Spanned lexers give the location of each token. Otherwise, tokens are
+    // located by their index.
+    let (next_item, found_pat, found, peek_token, lookahead, eof_error, parse_error_ty) =
+        if is_spanned {
+            (
+                next_raw_item,
+                quote! { (start, token, end) },
+                quote! { (start, token, end) },
+                quote! { #peek_raw_item.map(|(_, token, _)| token) },
+                quote! { #peek_raw_item.map_or_else(|| lookbehind.clone(), |(start, _, _)| start.clone()) },
+                quote! { ParseError::UnexpectedEOF { location: lookbehind.clone() } },
+                quote! { ParseError<#location_ty, #token_ty, #error_ty #lexer_error_arg> },
+            )
+        } else {
+            (
+                quote! { #next_raw_item.map(|token| (lookbehind, token, lookbehind + 1)) },
+                quote! { (_, token, _) },
+                quote! { token },
+                peek_raw_item,
+                quote! { lookbehind },
+                quote! { ParseError::UnexpectedEOF },
+                quote! { ParseError<#token_ty, #error_ty #lexer_error_arg> },
+            )
+        };
+
+    // Syntactic predicates match the buffered items against their terminals
+    let is_fallible = ast.extern_decl.lexer_error_ty.is_some();
+    let predicate_item_pat = |term: &str| {
+        if term == EOF_TERMINAL {
+            return quote! { None };
+        }
+
+        let pat = &token_pats[term];
+        let item_pat = if is_spanned {
+            quote! { (_, #pat, _) }
+        } else {
+            pat.clone()
+        };
+        if is_fallible {
+            quote! { Some(Ok(#item_pat)) }
+        } else {
+            quote! { Some(#item_pat) }
+        }
+    };
+    // The lexer is buffered up to the longest syntactic predicate
+    let lookahead_len = ast
+        .productions_indexed()
+        .flat_map(|(_, production, _)| &production.predicates)
+        .map(|predicate| match predicate {
+            AstPredicate::Syntactic(terms) => terms.len(),
+            AstPredicate::Semantic(_) => 1,
+        })
+        .max()
+        .unwrap_or(1);
+
+    // The error token is reported wherever a token is taken from the lexer
+    let invalid_token_check = ast.token_decl.error_pattern.as_ref().map(|pattern| {
+        let pattern = pattern.to_pattern_tokens(false);
+        quote! {
+            if matches!(token, #pattern) {
+                return Err(ParseError::InvalidToken(#found));
+            }
+        }
+    });
+
+    // The location nonterminals don't depend on the next token, so they're
+    // matched before the parse table
+    let location_matches = ast.productions().filter_map(|(nonterm, production)| {
+        let location = match production.action {
+            AstAction::Location(AstLocation::Start) => lookahead.clone(),
+            AstAction::Location(AstLocation::End) => quote! { lookbehind.clone() },
+            _ => return None,
+        };
+        let nonterm_ident = &names[nonterm];
+
+        Some(quote! {
+            (Symbol::#nonterm_ident, _) => {
+                let location = #location;
+                results.push((
+                    location.clone(),
+                    ActionResult::#nonterm_ident(location.clone()),
+                    location,
+                ));
+            }
+        })
+    });
+
+    // Folds reduce the values which were parsed before them, so they don't
+    // depend on the next token either
+    let fold_matches = ast.nonterminals.iter().flat_map(|nonterminal| {
+        nonterminal
+            .productions
+            .iter()
+            .filter(|production| matches!(production.action, AstAction::Fold(_, _)))
+            .map(move |production| {
+                let nonterm_ident = &names[nonterminal.name];
+                let production_id = production_ids[&(nonterminal.name, production)];
+                let reduction_fn = format_ident!("reduce_{}_{}", nonterm_ident, production_id);
+                let cfg_attrs = nonterminal
+                    .attrs
+                    .iter()
+                    .chain(&production.attrs)
+                    .filter(|attr| attr.is_cfg());
+
+                quote! {
+                    #(#cfg_attrs)*
+                    (Symbol::#nonterm_ident, _) => {
+                        stack.push(SymbolOrReduction::Reduction(#reduction_fn));
+                    }
+                }
+            })
+    });
 
     let parse_table_matches = parse_table
         .iter()
         .flat_map(|(key, productions)| productions.iter().map(move |production| (*key, production)))
+        .filter(|(_, production)| {
+            !matches!(
+                production.action,
+                AstAction::Location(_) | AstAction::Fold(_, _)
+            )
+        })
         .map(|((nonterm, next_token), production)| {
             let nonterm_ident = &names[nonterm];
+            // Productions which are configured out can't be parsed
+            let cfg_attrs = symbol_cfg_attrs(ast, nonterm)
+                .into_iter()
+                .chain(production.attrs.iter().filter(|attr| attr.is_cfg()));
 
             let token_pat = if next_token == EOF_TERMINAL {
                 quote! { None }
@@ -158,109 +396,434 @@ fn generate_parse_fn<'a>(
             };
             let production_id = production_ids[&(nonterm, production)];
             let reduction_fn = format_ident!("reduce_{}_{}", nonterm_ident, production_id);
-            let symbol_push_stmts = production.symbols.iter().rev().map(|symbol| {
-                let symbol_variant = match symbol {
-                    AstSymbol::Terminal(name)
-                    | AstSymbol::Nonterminal(name)
-                    | AstSymbol::Named(_, name) => &names[*name],
-                };
+            // Guarded productions are only chosen if all of their predicates hold
+            let predicate_guard = if production.predicates.is_empty() {
+                None
+            } else {
+                let conditions = production
+                    .predicates
+                    .iter()
+                    .map(|predicate| match predicate {
+                        AstPredicate::Semantic(code) => {
+                            let code = TokenStream2::from_str(code).unwrap();
+                            quote! { { let token = next_token; #code } }
+                        }
+                        AstPredicate::Syntactic(terms) => {
+                            let checks = terms.iter().enumerate().map(|(i, term)| {
+                                let pat = predicate_item_pat(term);
+                                quote! { matches!(lexer.peek_nth(#i), #pat) }
+                            });
+                            quote! { #(#checks)&&* }
+                        }
+                    });
+                Some(quote! { if #(#conditions)&&* })
+            };
+            let symbol_push_stmts =
+                production
+                    .symbols
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(j, symbol)| {
+                        let symbol_variant = &names[symbol.term_or_nonterm()];
+                        // The inputs of a nonterminal are passed down before it's parsed
+                        let inherit_push_stmt = call_args(symbol).map(|_| {
+                            let inherit_fn =
+                                format_ident!("inherit_{}_{}_{}", nonterm_ident, production_id, j);
+                            quote! { stack.push(SymbolOrReduction::Reduction(#inherit_fn)); }
+                        });
 
-                quote! {
-                    stack.push(SymbolOrReduction::Symbol(Symbol::#symbol_variant));
+                        quote! {
+                            stack.push(SymbolOrReduction::Symbol(Symbol::#symbol_variant));
+                            #inherit_push_stmt
+                        }
+                    });
+
+            // Repetitions mark where their values start, so they can be
+            // collected in one go once the last one is parsed.
+            let (reduction_push_stmt, start_push_stmt) = match production.action {
+                AstAction::Code(_) | AstAction::FallibleCode(_) | AstAction::Implicit => (
+                    Some(quote! { stack.push(SymbolOrReduction::Reduction(#reduction_fn)); }),
+                    None,
+                ),
+                AstAction::Collect(_) => (
+                    Some(quote! { stack.push(SymbolOrReduction::Reduction(#reduction_fn)); }),
+                    Some(quote! { stack.push(SymbolOrReduction::Reduction(start_repetition)); }),
+                ),
+                AstAction::Location(_) | AstAction::Passthrough | AstAction::Fold(_, _) => {
+                    (None, None)
                 }
-            });
+            };
 
             quote! {
-                (Symbol::#nonterm_ident, #token_pat) => {
-                    stack.push(SymbolOrReduction::Reduction(#reduction_fn));
+                #(#cfg_attrs)*
+                (Symbol::#nonterm_ident, #token_pat) #predicate_guard => {
+                    #reduction_push_stmt
                     #(#symbol_push_stmts)*
+                    #start_push_stmt
                 }
             }
         });
 
     let first_match_rules = first_map.iter().map(|(symbol, first_set)| {
         let canonical_name = &names[*symbol];
-        let mut first_vec: Vec<_> = first_set.iter().collect();
+        let cfg_attrs = symbol_cfg_attrs(ast, symbol);
+        let mut first_vec: Vec<_> = first_set.iter().map(|term| terminal_name(term)).collect();
         first_vec.sort();
         quote! {
+            #(#cfg_attrs)*
             Symbol::#canonical_name => vec![#(#first_vec),*],
         }
     });
 
+    // Each public nonterminal has an entry point which starts from its wrapper
+    let start_nonterms: Vec<_> = ast
+        .nonterminals
+        .iter()
+        .filter(|nonterminal| nonterminal.is_pub)
+        .collect();
+    let entry_fns = start_nonterms.iter().map(|wrapper| {
+        let start_name = wrapper.productions[0].symbols[0].term_or_nonterm();
+        let fn_name = format_ident!("parse_{}", to_snake_case(start_name));
+        let docs = &wrapper.docs;
+        let attrs = wrapper.attrs.iter().filter(|attr| attr.is_standard());
+        let return_ty = &wrapper.ty;
+        let canonical_name = &names[wrapper.name];
+        let pop_fn = format_ident!("pop_{}", canonical_name);
+
+        quote! {
+            #(#[doc = #docs])*
+            #(#attrs)*
+            pub fn #fn_name #params (
+                #(#param_names: #param_tys,)*
+                lexer: impl Iterator<Item = #lexer_item_ty>,
+            ) -> Result<#return_ty, #parse_error_ty> #where_clause {
+                let mut results: Vec<(#location_ty, ActionResult #args, #location_ty)> =
+                    run_parser(#(#param_names,)* Symbol::#canonical_name, lexer)?;
+                let (_, result, _) = #pop_fn(&mut results);
+                Ok(result)
+            }
+        }
+    });
+
+    // Grammars with one public nonterminal keep the plain `parse` entry point
+    let default_entry_fn = match start_nonterms.as_slice() {
+        [wrapper] => {
+            let start_name = wrapper.productions[0].symbols[0].term_or_nonterm();
+            let fn_name = format_ident!("parse_{}", to_snake_case(start_name));
+            let docs = &wrapper.docs;
+            let attrs = wrapper.attrs.iter().filter(|attr| attr.is_standard());
+            let return_ty = &wrapper.ty;
+
+            Some(quote! {
+                #(#[doc = #docs])*
+                #(#attrs)*
+                pub fn parse #params (
+                    #(#param_names: #param_tys,)*
+                    lexer: impl Iterator<Item = #lexer_item_ty>,
+                ) -> Result<#return_ty, #parse_error_ty> #where_clause {
+                    #fn_name #turbofish (#(#param_names,)* lexer)
+                }
+            })
+        }
+        _ => None,
+    };
+
     quote! {
-        pub fn parse(lexer: impl Iterator<Item = #token_ty>) -> Result<#return_ty, ParseError<#token_ty>> {
-            let mut lexer = lexer.peekable();
-            let mut stack = vec![SymbolOrReduction::Symbol(Symbol::#start_nonterm_canonical)];
+        #(#entry_fns)*
+        #default_entry_fn
+
+        /// The lexer, with a buffer of the items which were peeked at
+        struct TokenBuffer<I: Iterator> {
+            lexer: I,
+            peeked: std::collections::VecDeque<I::Item>,
+        }
+
+        impl<I: Iterator> TokenBuffer<I> {
+            fn next(&mut self) -> Option<I::Item> {
+                self.peeked.pop_front().or_else(|| self.lexer.next())
+            }
+
+            /// Buffer up to `len` items, if the lexer has that many left
+            fn fill(&mut self, len: usize) {
+                while self.peeked.len() < len {
+                    match self.lexer.next() {
+                        Some(item) => self.peeked.push_back(item),
+                        None => break,
+                    }
+                }
+            }
+
+            /// Get a buffered item
+            fn peek_nth(&self, n: usize) -> Option<&I::Item> {
+                self.peeked.get(n)
+            }
+        }
+
+        fn run_parser #params (
+            #(#param_names: #param_tys,)*
+            start: Symbol,
+            lexer: impl Iterator<Item = #lexer_item_ty>,
+        ) -> Result<Vec<(#location_ty, ActionResult #args, #location_ty)>, #parse_error_ty> #where_clause {
+            let mut lexer = TokenBuffer {
+                lexer,
+                peeked: std::collections::VecDeque::new(),
+            };
+            let mut stack: Vec<SymbolOrReduction #args> = vec![SymbolOrReduction::Symbol(start)];
             let mut results = Vec::new();
+            // The inputs passed down to the nonterminals being parsed
+            let mut inherited = Vec::new();
+            // The end of the last token
+            let mut lookbehind: #location_ty = Default::default();
 
             while let Some(item) = stack.pop() {
                 let symbol = match item {
                     SymbolOrReduction::Symbol(symbol) => symbol,
                     SymbolOrReduction::Reduction(reduction) => {
-                        reduction(&mut results);
+                        reduction(&mut results, &mut inherited #(, #param_names)*)
+                            .map_err(|error| ParseError::User { error })?;
                         continue;
                     }
                 };
 
                 if symbol.is_terminal() {
-                    let token = lexer.next();
+                    let item = #next_item;
 
                     if symbol.is_end() {
-                        if let Some(token) = token {
-                            return Err(ParseError::ExtraToken(token));
-                        } else {
-                            continue;
+                        if let Some(#found_pat) = item {
+                            #invalid_token_check
+                            return Err(ParseError::ExtraToken(#found));
                         }
+
+                        results.push((
+                            lookbehind.clone(),
+                            ActionResult::#end_variant(()),
+                            lookbehind.clone(),
+                        ));
+                        continue;
                     }
 
-                    let token = token.ok_or(ParseError::UnexpectedEOF)?;
+                    let (start, token, end) = item.ok_or(#eof_error)?;
 
-                    if symbol == token {
-                        continue;
-                    } else {
+                    if symbol != token {
+                        let #found_pat = (start, token, end);
+                        #invalid_token_check
                         return Err(ParseError::UnrecognizedToken {
                             expected: vec![symbol.name()],
-                            found: token,
+                            found: #found,
                         });
                     }
+
+                    lookbehind = end.clone();
+                    results.push((start, token_value(&symbol, token), end));
                 } else {
-                    let next_token = lexer.peek();
+                    lexer.fill(#lookahead_len);
+                    let next_token = #peek_token;
                     match (symbol, next_token) {
+                        #(#location_matches)*
+                        #(#fold_matches)*
                         #(#parse_table_matches)*
-                        (symbol, None) => return Err(ParseError::UnexpectedEOF),
+                        (symbol, None) => return Err(#eof_error),
                         (symbol, Some(_)) => {
+                            let #found_pat = #next_item.unwrap();
+                            #invalid_token_check
                             return Err(ParseError::UnrecognizedToken {
                                 expected: match symbol {
                                     #(#first_match_rules)*
                                 },
-                                found: lexer.next().unwrap(),
+                                found: #found,
                             });
                         }
                     }
                 }
             }
 
-            if let Some(token) = lexer.next() {
-                return Err(ParseError::ExtraToken(token))
+            if let Some(#found_pat) = #next_item {
+                #invalid_token_check
+                return Err(ParseError::ExtraToken(#found))
             }
 
-            Ok(#return_pop_fn(&mut results))
+            Ok(results)
         }
     }
 }
 
-fn generate_action_result_pop_fns(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
-    ast.nonterminals
-        .iter()
-        .map(|nonterminal| {
-            let canonical_name = &names[nonterminal.name];
+/// Generate the error type of the parser. Its variants carry the locations of
+/// errors if the lexer is spanned, and it has a variant for lexer errors if the
+/// lexer is fallible.
+fn generate_parse_error_enum(ast: &AstGrammar) -> TokenStream2 {
+    let is_spanned = ast.extern_decl.location_ty.is_some();
+    let error_ty = generate_error_ty(ast);
+    // The error types can only be defaults if they don't use the grammar's
+    // generic parameters
+    let default_ty = |ty: &AstTypeRef| {
+        let uses_generics = ast
+            .grammar_decl
+            .generics
+            .iter()
+            .any(|param| ty.mentions(param.name));
+
+        if uses_generics {
+            TokenStream2::new()
+        } else {
+            quote! { = #ty }
+        }
+    };
+    let error_default = match &ast.extern_decl.error_ty {
+        Some(ty) => default_ty(ty),
+        None => quote! { = #error_ty },
+    };
+
+    let (mut generics, mut variants) = if is_spanned {
+        (
+            vec![quote! { L }, quote! { T }, quote! { E #error_default }],
+            quote! {
+                UnexpectedEOF {
+                    location: L,
+                },
+                ExtraToken((L, T, L)),
+                UnrecognizedToken {
+                    expected: std::vec::Vec<&'static str>,
+                    found: (L, T, L),
+                },
+            },
+        )
+    } else {
+        (
+            vec![quote! { T }, quote! { E #error_default }],
+            quote! {
+                UnexpectedEOF,
+                ExtraToken(T),
+                UnrecognizedToken {
+                    expected: std::vec::Vec<&'static str>,
+                    found: T,
+                },
+            },
+        )
+    };
+
+    if ast.token_decl.error_pattern.is_some() {
+        variants.extend(if is_spanned {
+            quote! {
+                /// The lexer produced its error token
+                InvalidToken((L, T, L)),
+            }
+        } else {
+            quote! {
+                /// The lexer produced its error token
+                InvalidToken(T),
+            }
+        });
+    }
+
+    if let Some(lexer_error_ty) = &ast.extern_decl.lexer_error_ty {
+        let lexer_error_default = default_ty(lexer_error_ty);
+        generics.push(quote! { LE #lexer_error_default });
+        variants.extend(if is_spanned {
+            quote! {
+                /// The lexer returned an error after the given location
+                Lexer {
+                    error: LE,
+                    location: L,
+                },
+            }
+        } else {
+            quote! {
+                /// The lexer returned an error
+                Lexer(LE),
+            }
+        });
+    }
+
+    quote! {
+        /// An error from parsing. `User` is always present, so the type has the
+        /// same parameters whether or not the grammar has fallible actions
+        /// (`=>?`), but only those actions produce it.
+        #[derive(Debug, PartialEq)]
+        pub enum ParseError<#(#generics),*> {
+            #variants
+            /// A fallible action (`=>?`) returned an error. Grammars without
+            /// fallible actions never produce this.
+            User {
+                error: E,
+            },
+        }
+    }
+}
+
+/// Convert a nonterminal name to snake case for its entry point
+/// (`ExprList` -> `expr_list`)
+fn to_snake_case(name: &str) -> String {
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    let chars: Vec<_> = name.chars().collect();
+    let mut snake_case = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i != 0 && chars[i - 1] != '_' {
+            let prev_is_lower = !chars[i - 1].is_uppercase();
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            if prev_is_lower || next_is_lower {
+                snake_case.push('_');
+            }
+        }
+
+        snake_case.extend(c.to_lowercase());
+    }
+
+    snake_case
+}
+
+fn generate_action_result_pop_fns(
+    ast: &AstGrammar,
+    names: &NameMap,
+    symbol_tys: &SymbolTyMap,
+    generics: &Generics,
+) -> TokenStream2 {
+    let Generics {
+        params,
+        args,
+        where_clause,
+        ..
+    } = generics;
+    let location_ty = ast.location_ty();
+
+    let popped_symbols: HashSet<_> = ast
+        .productions()
+        .filter_map(|(_, production)| match production.action {
+            AstAction::Code(_) | AstAction::FallibleCode(_) | AstAction::Implicit => {
+                Some(production.symbols)
+            }
+            AstAction::Fold(_, folded) => Some(folded.symbols),
+            _ => None,
+        })
+        .flatten()
+        .map(|symbol| symbol.term_or_nonterm())
+        // The public nonterminals are popped at the end of parsing
+        .chain(
+            ast.nonterminals
+                .iter()
+                .filter(|nonterminal| nonterminal.is_pub)
+                .map(|nonterminal| nonterminal.name),
+        )
+        .collect();
+
+    ast.terminals()
+        .chain(ast.nonterminals())
+        .filter(|symbol| popped_symbols.contains(symbol))
+        .map(|symbol| {
+            let canonical_name = &names[symbol];
             let fn_name = format_ident!("pop_{}", canonical_name);
-            let return_ty = &nonterminal.ty;
+            let return_ty = &symbol_tys[symbol];
+            let cfg_attrs = symbol_cfg_attrs(ast, symbol);
 
             quote! {
-                fn #fn_name(results: &mut Vec<ActionResult>) -> #return_ty {
+                #(#cfg_attrs)*
+                fn #fn_name #params (
+                    results: &mut Vec<(#location_ty, ActionResult #args, #location_ty)>,
+                ) -> (#location_ty, #return_ty, #location_ty) #where_clause {
                     match results.pop() {
-                        Some(ActionResult::#canonical_name(value)) => value,
+                        Some((start, ActionResult::#canonical_name(value), end)) => (start, value, end),
                         _ => panic!("Unexpected action result"),
                     }
                 }
@@ -269,96 +832,484 @@ fn generate_action_result_pop_fns(ast: &AstGrammar, names: &NameMap) -> TokenStr
         .collect()
 }
 
+/// Generate the reduction which marks the start of a repetition's values, if
+/// the grammar has any repetitions.
+fn generate_repetition_fns(
+    ast: &AstGrammar,
+    generics: &Generics,
+    grammar_params: &GrammarParams,
+) -> TokenStream2 {
+    let has_repetitions = ast
+        .productions()
+        .any(|(_, production)| matches!(production.action, AstAction::Collect(_)));
+
+    if !has_repetitions {
+        return TokenStream2::new();
+    }
+
+    let Generics {
+        params,
+        args,
+        where_clause,
+        ..
+    } = generics;
+    let param_tys = &grammar_params.tys;
+    let error_ty = generate_error_ty(ast);
+    let location_ty = ast.location_ty();
+    quote! {
+        fn start_repetition #params (
+            results: &mut Vec<(#location_ty, ActionResult #args, #location_ty)>,
+            _: &mut Vec<ActionResult #args>,
+            #(_: #param_tys),*
+        ) -> Result<(), #error_ty> #where_clause {
+            let location = last_location(results);
+            results.push((location.clone(), ActionResult::RepetitionStart, location));
+            Ok(())
+        }
+    }
+}
+
 fn generate_action_fns(
     ast: &AstGrammar,
     names: &NameMap,
-    nonterm_tys: &NontermTyMap,
+    symbol_tys: &SymbolTyMap,
+    generics: &Generics,
+    grammar_params: &GrammarParams,
 ) -> TokenStream2 {
+    let Generics {
+        params,
+        where_clause,
+        ..
+    } = generics;
+    let GrammarParams {
+        names: param_names,
+        tys: param_tys,
+    } = grammar_params;
+    let error_ty = generate_error_ty(ast);
+
     ast.productions_indexed()
-        .map(|(nonterminal, production, i)| {
+        .filter_map(|(nonterminal, production, i)| {
+            let inputs = visible_inputs(ast, nonterminal);
+            // Not every action uses the grammar parameters and inputs
+            let allow_unused = if param_names.is_empty() && inputs.is_empty() {
+                TokenStream2::new()
+            } else {
+                quote! { #[allow(unused_variables)] }
+            };
+            let input_params = inputs.iter().map(|input| {
+                let name_ident = rust_ident(input.name);
+                let ty = &input.ty;
+                quote! { #name_ident: &#ty }
+            });
+            let production = folded_production(production);
+            let code = match production.action {
+                AstAction::Code(code) | AstAction::FallibleCode(code) => {
+                    replace_placeholders(code, &selected_value(production))
+                }
+                AstAction::Implicit => selected_value(production),
+                AstAction::Location(_)
+                | AstAction::Collect(_)
+                | AstAction::Passthrough
+                | AstAction::Fold(_, _) => return None,
+            };
+            let code = TokenStream2::from_str(&code).unwrap();
             let canonical_name = &names[nonterminal.name];
             let fn_name = format_ident!("action_{}_{}", canonical_name, i);
-            let params = production.symbols.iter().filter_map(|symbol| match symbol {
-                AstSymbol::Named(name, nonterm) => {
-                    let name_ident = Ident::new(name, Span::call_site());
-                    let param_ty = nonterm_tys[*nonterm];
+            let action_params = production.symbols.iter().filter_map(|symbol| match symbol {
+                AstSymbol::Named(name, symbol) => {
+                    let name_ident = rust_ident(name);
+                    let param_ty = &symbol_tys[symbol.term_or_nonterm()];
                     Some(quote! { #name_ident: #param_ty })
                 }
                 _ => None,
             });
-            let return_ty = &nonterminal.ty;
-            let code = TokenStream2::from_str(production.code).unwrap();
-
-            quote! {
-                fn #fn_name(#(#params),*) -> #return_ty {
+            let ty = &nonterminal.ty;
+            let return_ty = match production.action {
+                AstAction::FallibleCode(_) => quote! { Result<#ty, #error_ty> },
+                _ => quote! { #ty },
+            };
+            let attrs = standard_attrs(nonterminal, production);
+
+            Some(quote! {
+                #(#attrs)*
+                #allow_unused
+                fn #fn_name #params (
+                    #(#param_names: #param_tys,)*
+                    #(#input_params,)*
+                    #(#action_params),*
+                ) -> #return_ty #where_clause {
                     #code
                 }
-            }
+            })
         })
         .collect()
 }
 
-fn generate_reduce_fns(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
+/// Get the production which is reduced by a fold, or the production itself
+fn folded_production<'a, 'input>(
+    production: &'a AstProduction<'input>,
+) -> &'a AstProduction<'input> {
+    match &production.action {
+        AstAction::Fold(_, folded) => folded,
+        _ => production,
+    }
+}
+
+/// Get the `cfg` attributes of a nonterminal, which are copied onto every item
+/// generated for it (its symbol, result and functions), so they're configured
+/// out together. Terminals don't have any.
+fn symbol_cfg_attrs<'a, 'input>(
+    ast: &'a AstGrammar<'input>,
+    symbol: &str,
+) -> Vec<&'a AstAttribute<'input>> {
+    ast.nonterminals
+        .iter()
+        .find(|nonterminal| nonterminal.name == symbol)
+        .into_iter()
+        .flat_map(|nonterminal| &nonterminal.attrs)
+        .filter(|attr| attr.is_cfg())
+        .collect()
+}
+
+/// Get the standard attributes of a nonterminal and one of its productions,
+/// which are copied onto the functions generated for the production
+fn standard_attrs<'a, 'input>(
+    nonterminal: &'a AstNonterminal<'input>,
+    production: &'a AstProduction<'input>,
+) -> Vec<&'a AstAttribute<'input>> {
+    nonterminal
+        .attrs
+        .iter()
+        .chain(&production.attrs)
+        .filter(|attr| attr.is_standard())
+        .collect()
+}
+
+/// Get the value of the production's named symbols, as a tuple if there are
+/// several. Implicit actions return this, and `<>` in action code expands to
+/// it.
+fn selected_value(production: &AstProduction) -> String {
+    let names: Vec<_> = production
+        .symbols
+        .iter()
+        .filter_map(|symbol| match symbol {
+            AstSymbol::Named(name, _) => Some(*name),
+            _ => None,
+        })
+        .collect();
+
+    match names.as_slice() {
+        [name] => name.to_string(),
+        _ => format!("({})", names.join(", ")),
+    }
+}
+
+fn generate_reduce_fns(
+    ast: &AstGrammar,
+    names: &NameMap,
+    generics: &Generics,
+    grammar_params: &GrammarParams,
+) -> TokenStream2 {
+    let Generics {
+        params,
+        args,
+        turbofish,
+        where_clause,
+    } = generics;
+    let GrammarParams {
+        names: param_names,
+        tys: param_tys,
+    } = grammar_params;
+    let error_ty = generate_error_ty(ast);
+    let location_ty = ast.location_ty();
+
     ast.productions_indexed()
-        .map(|(nonterminal, production, i)| {
+        .filter_map(|(nonterminal, production, i)| {
             let canonical_name = &names[nonterminal.name];
             let reduce_fn_name = format_ident!("reduce_{}_{}", canonical_name, i);
-            let params = production
+            // Folds give a value of the nonterminal they were lowered from
+            let result_variant = match production.action {
+                AstAction::Fold(target, _) => &names[target],
+                _ => canonical_name,
+            };
+            let production = folded_production(production);
+            let attrs = standard_attrs(nonterminal, production);
+
+            // Errors from fallible actions are passed up to the parser
+            let try_op = match production.action {
+                AstAction::Code(_) | AstAction::Implicit => None,
+                AstAction::FallibleCode(_) => Some(quote! { ? }),
+                AstAction::Collect(element) => {
+                    let element_variant = &names[element];
+                    return Some(quote! {
+                        #(#attrs)*
+                        fn #reduce_fn_name #params (
+                            results: &mut Vec<(#location_ty, ActionResult #args, #location_ty)>,
+                            _: &mut Vec<ActionResult #args>,
+                            #(_: #param_tys),*
+                        ) -> Result<(), #error_ty> #where_clause {
+                            let mut items = Vec::new();
+                            let start_location = loop {
+                                match results.pop() {
+                                    Some((start, ActionResult::#element_variant(value), end)) => {
+                                        items.push((start, value, end))
+                                    }
+                                    Some((start, ActionResult::RepetitionStart, _)) => break start,
+                                    _ => panic!("Unexpected action result"),
+                                }
+                            };
+                            items.reverse();
+
+                            // An empty repetition is located where it started
+                            let (span_start, span_end) = match (items.first(), items.last()) {
+                                (Some((start, _, _)), Some((_, _, end))) => (start.clone(), end.clone()),
+                                _ => (start_location.clone(), start_location),
+                            };
+                            let values = items.into_iter().map(|(_, value, _)| value).collect();
+                            results.push((span_start, ActionResult::#canonical_name(values), span_end));
+                            Ok(())
+                        }
+                    });
+                }
+                AstAction::Location(_) | AstAction::Passthrough | AstAction::Fold(_, _) => {
+                    return None
+                }
+            };
+
+            let symbols = production
                 .symbols
                 .iter()
-                .filter_map(|symbol| match symbol {
-                    AstSymbol::Nonterminal(nonterm) => Some((false, *nonterm)),
-                    AstSymbol::Named(_, nonterm) => Some((true, nonterm)),
-                    AstSymbol::Terminal(_) => None,
-                })
+                .map(|symbol| (matches!(symbol, AstSymbol::Named(_, _)), symbol.term_or_nonterm()))
                 .enumerate();
-            let mut param_stmts: Vec<_> = params
+            // The production spans from the start of its first symbol to the
+            // end of its last symbol
+            let symbol_count = production.symbols.len();
+            let mut param_stmts: Vec<_> = symbols
                 .clone()
-                .map(|(j, (is_named, nonterm))| {
-                    let canonical_nonterm_name = &names[nonterm];
-                    let pop_fn = format_ident!("pop_{}", canonical_nonterm_name);
-
-                    if is_named {
-                        let param_name = format_ident!("param{}", j);
-                        quote! { let #param_name = #pop_fn(results); }
+                .map(|(j, (is_named, symbol))| {
+                    let canonical_symbol_name = &names[symbol];
+                    let pop_fn = format_ident!("pop_{}", canonical_symbol_name);
+                    let start_pat = if j == 0 {
+                        quote! { span_start }
                     } else {
-                        quote! { #pop_fn(results); }
-                    }
+                        quote! { _ }
+                    };
+                    let value_pat = if is_named {
+                        format_ident!("param{}", j).into_token_stream()
+                    } else {
+                        quote! { _ }
+                    };
+                    let end_pat = if j == symbol_count - 1 {
+                        quote! { span_end }
+                    } else {
+                        quote! { _ }
+                    };
+
+                    quote! { let (#start_pat, #value_pat, #end_pat) = #pop_fn(results); }
                 })
                 .collect();
             param_stmts.reverse();
+            if symbol_count == 0 {
+                param_stmts.push(quote! {
+                    let span_start = last_location(results);
+                    let span_end = span_start.clone();
+                });
+            }
             let action_fn = format_ident!("action_{}_{}", canonical_name, i);
-            let action_params = params.filter_map(|(j, (is_named, _))| {
+            let action_params = symbols.filter_map(|(j, (is_named, _))| {
                 if !is_named {
                     return None;
                 }
                 Some(format_ident!("param{}", j))
             });
+            let (input_stmt, input_names) = input_bindings(ast, names, nonterminal);
+            // The inputs are dropped once the nonterminal they were passed to is reduced
+            let input_pop_stmt = if nonterminal.inputs.is_empty() {
+                None
+            } else {
+                Some(quote! { inherited.pop(); })
+            };
 
-            quote! {
-                fn #reduce_fn_name(results: &mut Vec<ActionResult>) {
+            Some(quote! {
+                #(#attrs)*
+                fn #reduce_fn_name #params (
+                    results: &mut Vec<(#location_ty, ActionResult #args, #location_ty)>,
+                    inherited: &mut Vec<ActionResult #args>,
+                    #(#param_names: #param_tys),*
+                ) -> Result<(), #error_ty> #where_clause {
                     #(#param_stmts)*
-                    let result = #action_fn(#(#action_params),*);
-                    results.push(ActionResult::#canonical_name(result));
+                    #input_stmt
+                    let result = #action_fn #turbofish (
+                        #(#param_names,)* #(#input_names,)* #(#action_params),*
+                    ) #try_op;
+                    #input_pop_stmt
+                    results.push((span_start, ActionResult::#result_variant(result), span_end));
+                    Ok(())
                 }
-            }
+            })
         })
         .collect()
 }
 
+/// Generate the reductions which compute the inputs of the nonterminals used
+/// with arguments (`Declarator(ty.clone())`). They run right before the
+/// nonterminal is parsed, so the arguments can use the values of the symbols
+/// before it in the production.
+fn generate_inherit_fns(
+    ast: &AstGrammar,
+    names: &NameMap,
+    generics: &Generics,
+    grammar_params: &GrammarParams,
+) -> TokenStream2 {
+    let Generics {
+        params,
+        args,
+        where_clause,
+        ..
+    } = generics;
+    let GrammarParams {
+        names: param_names,
+        tys: param_tys,
+    } = grammar_params;
+    let error_ty = &generate_error_ty(ast);
+    let location_ty = &ast.location_ty();
+
+    ast.productions_indexed()
+        .flat_map(|(nonterminal, production, i)| {
+            let canonical_name = &names[nonterminal.name];
+            let attrs = standard_attrs(nonterminal, production);
+            let (input_stmt, _) = input_bindings(ast, names, nonterminal);
+
+            production
+                .symbols
+                .iter()
+                .enumerate()
+                .filter_map(|(j, symbol)| Some((j, symbol, call_args(symbol)?)))
+                .map(move |(j, symbol, call_args)| {
+                    let fn_name = format_ident!("inherit_{}_{}_{}", canonical_name, i, j);
+                    let inputs_variant = format_ident!("{}Inputs", names[symbol.term_or_nonterm()]);
+                    let call_args = TokenStream2::from_str(call_args).unwrap();
+
+                    // Each symbol before the nonterminal has left one value
+                    let value_stmts = production.symbols[..j]
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(k, symbol)| match symbol {
+                            AstSymbol::Named(name, inner) => Some((k, name, inner)),
+                            _ => None,
+                        })
+                        .map(|(k, name, inner)| {
+                            let name_ident = rust_ident(name);
+                            let variant = &names[inner.term_or_nonterm()];
+                            let offset = j - k;
+
+                            quote! {
+                                let #name_ident = match &results[results.len() - #offset] {
+                                    (_, ActionResult::#variant(value), _) => value,
+                                    _ => panic!("Unexpected action result"),
+                                };
+                            }
+                        });
+
+                    quote! {
+                        #(#attrs)*
+                        #[allow(unused_variables)]
+                        fn #fn_name #params (
+                            results: &mut Vec<(#location_ty, ActionResult #args, #location_ty)>,
+                            inherited: &mut Vec<ActionResult #args>,
+                            #(#param_names: #param_tys),*
+                        ) -> Result<(), #error_ty> #where_clause {
+                            #(#value_stmts)*
+                            #input_stmt
+                            let inputs = (#call_args,);
+                            inherited.push(ActionResult::#inputs_variant(inputs));
+                            Ok(())
+                        }
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Get the arguments of a symbol which passes inputs down to its nonterminal
+fn call_args<'input>(symbol: &AstSymbol<'input>) -> Option<&'input str> {
+    match symbol {
+        AstSymbol::Call(_, args) => Some(args),
+        AstSymbol::Named(_, inner) => call_args(inner),
+        _ => None,
+    }
+}
+
+/// Get the inputs which the actions of a nonterminal can use: its own, or those
+/// of the nonterminal it was generated from
+fn visible_inputs<'a, 'input>(
+    ast: &'a AstGrammar<'input>,
+    nonterminal: &'a AstNonterminal<'input>,
+) -> &'a [AstGrammarParam<'input>] {
+    let scope = match nonterminal.input_scope {
+        Some(scope) => ast
+            .nonterminals
+            .iter()
+            .find(|nonterminal| nonterminal.name == scope)
+            .unwrap(),
+        None => nonterminal,
+    };
+
+    &scope.inputs
+}
+
+/// Generate the statement which borrows the inputs visible to a nonterminal
+/// from the top of the inherited stack, and the names it binds
+fn input_bindings(
+    ast: &AstGrammar,
+    names: &NameMap,
+    nonterminal: &AstNonterminal,
+) -> (Option<TokenStream2>, Vec<Ident>) {
+    let inputs = visible_inputs(ast, nonterminal);
+    if inputs.is_empty() {
+        return (None, Vec::new());
+    }
+
+    let owner = nonterminal.input_scope.unwrap_or(nonterminal.name);
+    let inputs_variant = format_ident!("{}Inputs", names[owner]);
+    let input_names: Vec<_> = inputs.iter().map(|input| rust_ident(input.name)).collect();
+    let stmt = quote! {
+        let (#(#input_names,)*) = match inherited.last() {
+            Some(ActionResult::#inputs_variant(inputs)) => inputs,
+            _ => panic!("Unexpected inherited inputs"),
+        };
+    };
+
+    (Some(stmt), input_names)
+}
+
 fn generate_partial_eq_impl(
     ast: &AstGrammar,
     names: &NameMap,
     token_pats: &TokenPatMap,
 ) -> TokenStream2 {
-    let token_type = Ident::new(ast.token_decl.name, Span::call_site());
+    let token_type = &ast.token_decl.ty;
+    // Only the generic parameters used by the token type can be declared on
+    // its impls
+    let token_params: Vec<_> = ast
+        .grammar_decl
+        .generics
+        .iter()
+        .filter(|param| token_type.mentions(param.name))
+        .collect();
+    let impl_params = if token_params.is_empty() {
+        TokenStream2::new()
+    } else {
+        quote! { <#(#token_params),*> }
+    };
     let match_actions = token_pats.iter().map(|(term, token_pat)| {
         let symbol_variant = &names[*term];
         quote! { Symbol::#symbol_variant => matches!(other, #token_pat), }
     });
 
     quote! {
-        impl PartialEq<#token_type> for Symbol {
+        impl #impl_params PartialEq<#token_type> for Symbol {
             fn eq(&self, other: &#token_type) -> bool{
                 match self {
                     #(#match_actions)*
@@ -366,6 +1317,55 @@ fn generate_partial_eq_impl(
                 }
             }
         }
+
+        impl #impl_params PartialEq<Symbol> for #token_type {
+            fn eq(&self, other: &Symbol) -> bool {
+                other.eq(self)
+            }
+        }
+    }
+}
+
+fn generate_token_value_fn(ast: &AstGrammar, names: &NameMap, generics: &Generics) -> TokenStream2 {
+    let token_type = &ast.token_decl.ty;
+    let Generics {
+        params,
+        args,
+        where_clause,
+        ..
+    } = generics;
+    let match_arms = ast.token_decl.aliases.iter().map(|token_alias| {
+        let symbol_variant = &names[token_alias.term];
+        let pattern = &token_alias.pattern;
+        let payload_count = pattern.payload_tys().len();
+
+        if payload_count == 0 {
+            return quote! {
+                (Symbol::#symbol_variant, token) => ActionResult::#symbol_variant(token)
+            };
+        }
+
+        let binding_pattern = pattern.to_pattern_tokens(true);
+        let payloads = (0..payload_count).map(|i| format_ident!("__payload{}", i));
+        let value = if payload_count == 1 {
+            quote! { #(#payloads)* }
+        } else {
+            quote! { (#(#payloads),*) }
+        };
+
+        quote! {
+            (Symbol::#symbol_variant, #binding_pattern) => ActionResult::#symbol_variant(#value)
+        }
+    });
+
+    quote! {
+        /// Move the value out of a token which was matched by the symbol
+        fn token_value #params (symbol: &Symbol, token: #token_type) -> ActionResult #args #where_clause {
+            match (symbol, token) {
+                #(#match_arms,)*
+                _ => unreachable!("Token does not match the symbol"),
+            }
+        }
     }
 }
 
@@ -380,13 +1380,15 @@ fn generate_symbol_impl(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
 
     let term_names = ast.terminals().map(|term| {
         let variant = &names[term];
-        let term = &term[1..(term.len() - 1)];
+        let term = terminal_name(term);
         quote! { Symbol::#variant => #term }
     });
 
     let nonterm_names = ast.nonterminals().map(|nonterm| {
         let variant = &names[nonterm];
-        quote! { Symbol::#variant => #nonterm }
+        let cfg_attrs = symbol_cfg_attrs(ast, nonterm);
+        let nonterm = nonterm.strip_prefix("r#").unwrap_or(nonterm);
+        quote! { #(#cfg_attrs)* Symbol::#variant => #nonterm }
     });
 
     let end_variant = &names[EOF_TERMINAL];
@@ -411,10 +1413,12 @@ fn generate_symbol_impl(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
 }
 
 fn generate_symbol_enum(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
-    let fields = ast
-        .terminals()
-        .chain(ast.nonterminals())
-        .map(|name| &names[name]);
+    let fields = ast.terminals().chain(ast.nonterminals()).map(|symbol| {
+        let name = &names[symbol];
+        let cfg_attrs = symbol_cfg_attrs(ast, symbol);
+
+        quote! { #(#cfg_attrs)* #name }
+    });
 
     quote! {
         enum Symbol {
@@ -423,25 +1427,186 @@ fn generate_symbol_enum(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
     }
 }
 
-fn generate_action_result_enum(ast: &AstGrammar, names: &NameMap) -> TokenStream2 {
-    let fields: Vec<_> = ast
+fn generate_action_result_enum(
+    ast: &AstGrammar,
+    names: &NameMap,
+    symbol_tys: &SymbolTyMap,
+    generics: &Generics,
+) -> TokenStream2 {
+    // Passthrough nonterminals leave their values to be collected by a
+    // repetition or reduced by a fold, and folds give a value of another
+    // nonterminal, so they never have a result of their own.
+    let fold_targets: HashSet<_> = ast
+        .productions()
+        .filter_map(|(_, production)| match production.action {
+            AstAction::Fold(target, _) => Some(target),
+            _ => None,
+        })
+        .collect();
+    let resultless_nonterms: HashSet<_> = ast
         .nonterminals
         .iter()
-        .map(|nonterm| {
-            let name = &names[nonterm.name];
-            let ty = &nonterm.ty;
+        .filter(|nonterminal| is_passthrough(nonterminal) || is_fold(nonterminal))
+        .map(|nonterminal| nonterminal.name)
+        .filter(|name| !fold_targets.contains(name))
+        .collect();
+    let mut fields: Vec<_> = ast
+        .terminals()
+        .chain(ast.nonterminals())
+        .filter(|symbol| !resultless_nonterms.contains(symbol))
+        .map(|symbol| {
+            let name = &names[symbol];
+            let ty = &symbol_tys[symbol];
+            let cfg_attrs = symbol_cfg_attrs(ast, symbol);
 
-            quote! { #name(#ty) }
+            quote! { #(#cfg_attrs)* #name(#ty) }
         })
         .collect();
 
+    let has_repetitions = ast
+        .productions()
+        .any(|(_, production)| matches!(production.action, AstAction::Collect(_)));
+    if has_repetitions {
+        fields.push(quote! { RepetitionStart });
+    }
+
+    // The inputs passed down to nonterminals are kept on their own stack
+    fields.extend(
+        ast.nonterminals
+            .iter()
+            .filter(|nonterminal| !nonterminal.inputs.is_empty())
+            .map(|nonterminal| {
+                let name = format_ident!("{}Inputs", names[nonterminal.name]);
+                let tys = nonterminal.inputs.iter().map(|input| &input.ty);
+                let cfg_attrs = nonterminal.attrs.iter().filter(|attr| attr.is_cfg());
+
+                quote! { #(#cfg_attrs)* #name((#(#tys,)*)) }
+            }),
+    );
+
+    // The generic parameters might not be used by any of the symbol types
+    let grammar_generics = &ast.grammar_decl.generics;
+    if !grammar_generics.is_empty() {
+        let markers = grammar_generics.iter().map(|param| {
+            let name = generic_name_tokens(param.name);
+            if param.is_lifetime() {
+                quote! { &#name () }
+            } else {
+                quote! { *const #name }
+            }
+        });
+
+        fields.push(quote! {
+            #[allow(dead_code)]
+            Phantom(std::marker::PhantomData<(#(#markers,)*)>)
+        });
+    }
+
+    let Generics {
+        params,
+        where_clause,
+        ..
+    } = generics;
     quote! {
-        enum ActionResult {
+        enum ActionResult #params #where_clause {
             #(#fields),*
         }
     }
 }
 
+/// The grammar's generic parameters, in the forms used by the generated items.
+/// These are all empty if the grammar has no generic parameters.
+struct Generics {
+    /// The parameters with their bounds, for declaring items (`<'input, T: Copy>`)
+    params: TokenStream2,
+    /// The parameters, for naming generic types (`<'input, T>`)
+    args: TokenStream2,
+    /// The type parameters, for calling functions which can't infer them
+    /// (`::<T>`)
+    turbofish: TokenStream2,
+    where_clause: TokenStream2,
+}
+
+fn generate_generics(ast: &AstGrammar) -> Generics {
+    let grammar_decl = &ast.grammar_decl;
+    let generics = &grammar_decl.generics;
+    let names: Vec<_> = generics
+        .iter()
+        .map(|param| generic_name_tokens(param.name))
+        .collect();
+    let ty_names: Vec<_> = generics
+        .iter()
+        .filter(|param| !param.is_lifetime())
+        .map(|param| generic_name_tokens(param.name))
+        .collect();
+
+    let (params, args) = if generics.is_empty() {
+        (TokenStream2::new(), TokenStream2::new())
+    } else {
+        (quote! { <#(#generics),*> }, quote! { <#(#names),*> })
+    };
+    let turbofish = if ty_names.is_empty() {
+        TokenStream2::new()
+    } else {
+        quote! { ::<#(#ty_names),*> }
+    };
+    let where_clause = if grammar_decl.where_clauses.is_empty() {
+        TokenStream2::new()
+    } else {
+        let predicates = &grammar_decl.where_clauses;
+        quote! { where #(#predicates),* }
+    };
+
+    Generics {
+        params,
+        args,
+        turbofish,
+        where_clause,
+    }
+}
+
+/// The grammar's parameters, which are passed through the parser to every
+/// action. They're passed once per reduction, so they should be references or
+/// `Copy` values.
+struct GrammarParams<'a> {
+    names: Vec<Ident>,
+    tys: Vec<&'a AstTypeRef<'a>>,
+}
+
+fn generate_grammar_params<'a>(ast: &'a AstGrammar) -> GrammarParams<'a> {
+    let params = &ast.grammar_decl.params;
+
+    GrammarParams {
+        names: params.iter().map(|param| rust_ident(param.name)).collect(),
+        tys: params.iter().map(|param| &param.ty).collect(),
+    }
+}
+
+/// Get the error type of fallible actions. It defaults to `&'static str` if the
+/// grammar doesn't declare one.
+fn generate_error_ty(ast: &AstGrammar) -> TokenStream2 {
+    match &ast.extern_decl.error_ty {
+        Some(ty) => ty.to_token_stream(),
+        None => quote! { &'static str },
+    }
+}
+
+/// Get the name of a terminal which is shown in errors: its text, without the
+/// quotes and escapes of its canonical form
+fn terminal_name(term: &str) -> String {
+    unescape(&term[1..(term.len() - 1)]).unwrap()
+}
+
+/// Get the identifier for a name in the grammar, which may be a raw identifier
+fn rust_ident(name: &str) -> Ident {
+    format_ident!("{}", name)
+}
+
+/// Get the tokens for a lifetime or type parameter name
+fn generic_name_tokens(name: &str) -> TokenStream2 {
+    TokenStream2::from_str(name).unwrap()
+}
+
 /// Generate a map from written down terminal/nonterminal names to "canonical"
 /// names like Term0 and Nonterm1.
 fn generate_name_map<'input>(ast: &AstGrammar<'input>) -> NameMap<'input> {
@@ -461,24 +1626,43 @@ fn generate_name_map<'input>(ast: &AstGrammar<'input>) -> NameMap<'input> {
     names
 }
 
-/// Generate a map from nonterminal name to the returned Rust type.
-fn generate_nonterm_ty_map<'input>(ast: &'input AstGrammar<'input>) -> NontermTyMap<'input> {
-    ast.nonterminals
-        .iter()
-        .map(|nonterminal| (nonterminal.name, &nonterminal.ty))
+/// Generate a map from terminal/nonterminal name to the Rust type of its value.
+fn generate_symbol_ty_map<'input>(ast: &AstGrammar<'input>) -> SymbolTyMap<'input> {
+    ast.terminals()
+        .chain(ast.nonterminals())
+        .map(|symbol| (symbol, ast.symbol_ty(symbol).unwrap()))
         .collect()
 }
 
+/// Check if the nonterminal only exists to parse the items of a repetition or
+/// the operators of a precedence level
+fn is_passthrough(nonterminal: &AstNonterminal) -> bool {
+    !nonterminal.productions.is_empty()
+        && nonterminal
+            .productions
+            .iter()
+            .all(|production| production.action == AstAction::Passthrough)
+}
+
+/// Check if the nonterminal only exists to reduce an operator's production
+fn is_fold(nonterminal: &AstNonterminal) -> bool {
+    !nonterminal.productions.is_empty()
+        && nonterminal
+            .productions
+            .iter()
+            .all(|production| matches!(production.action, AstAction::Fold(_, _)))
+}
+
 /// Generate a map from terminal alias to the Rust pattern that matches it.
 fn generate_token_pat_map<'input>(ast: &AstGrammar<'input>) -> TokenPatMap<'input> {
     ast.token_decl
         .aliases
         .iter()
         .map(|token_alias| {
-            let token_type = Ident::new(token_alias.pattern.ty, Span::call_site());
-            let token_variant = Ident::new(token_alias.pattern.variant, Span::call_site());
-
-            (token_alias.term, quote! { #token_type::#token_variant })
+            (
+                token_alias.term,
+                token_alias.pattern.to_pattern_tokens(false),
+            )
         })
         .collect()
 }
@@ -503,26 +1687,209 @@ impl<'input> ToTokens for AstTypeRef<'input> {
                 }
             }
             AstTypeRef::Tuple(tys) => {
+                // The trailing comma keeps a single element tuple from being
+                // a parenthesized type
+                tokens.extend(quote! {
+                    ( #(#tys,)* )
+                });
+            }
+            AstTypeRef::Ref {
+                lifetime,
+                is_mut,
+                ty,
+            } => {
+                let lifetime = lifetime.map(generic_name_tokens);
+                let mutability = is_mut.then(|| quote! { mut });
+                tokens.extend(quote! {
+                    & #lifetime #mutability #ty
+                });
+            }
+            AstTypeRef::Path {
+                qself,
+                is_absolute,
+                segments,
+            } => {
+                if let Some(AstQSelf { ty, as_trait }) = qself {
+                    let as_trait = as_trait.as_ref().map(|as_trait| quote! { as #as_trait });
+                    tokens.extend(quote! { < #ty #as_trait > :: });
+                } else if *is_absolute {
+                    tokens.extend(quote! { :: });
+                }
+
                 tokens.extend(quote! {
-                    ( #(#tys),* )
+                    #(#segments)::*
+                });
+            }
+            AstTypeRef::Paren(ty) => tokens.extend(quote! { ( #ty ) }),
+            AstTypeRef::Slice(ty) => tokens.extend(quote! { [ #ty ] }),
+            AstTypeRef::Array(ty, len) => {
+                let len = TokenStream2::from_str(len).unwrap();
+                tokens.extend(quote! { [ #ty; #len ] });
+            }
+            AstTypeRef::Ptr { is_mut, ty } => {
+                let mutability = if *is_mut {
+                    quote! { mut }
+                } else {
+                    quote! { const }
+                };
+                tokens.extend(quote! { * #mutability #ty });
+            }
+            AstTypeRef::FnPtr {
+                is_unsafe,
+                abi,
+                params,
+                ret,
+            } => {
+                let unsafety = is_unsafe.then(|| quote! { unsafe });
+                let abi = abi.map(|abi| {
+                    let abi = TokenStream2::from_str(abi).unwrap();
+                    quote! { extern #abi }
+                });
+                let ret = ret.as_ref().map(|ret| quote! { -> #ret });
+                tokens.extend(quote! {
+                    #unsafety #abi fn ( #(#params),* ) #ret
+                });
+            }
+            AstTypeRef::DynTrait(bounds) => tokens.extend(quote! { dyn #(#bounds)+* }),
+            AstTypeRef::ImplTrait(bounds) => tokens.extend(quote! { impl #(#bounds)+* }),
+            AstTypeRef::Never => tokens.extend(quote! { ! }),
+            AstTypeRef::Lifetime(lifetime) => tokens.extend(generic_name_tokens(lifetime)),
+            AstTypeRef::AssocType(name, ty) => {
+                let name = rust_ident(name);
+                tokens.extend(quote! { #name = #ty });
+            }
+        }
+    }
+}
+
+impl<'input> ToTokens for AstPathSegment<'input> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        rust_ident(self.name).to_tokens(tokens);
+
+        match &self.args {
+            AstGenericArgs::None => {}
+            AstGenericArgs::AngleBracketed(generics) => tokens.extend(quote! {
+                < #(#generics),* >
+            }),
+            AstGenericArgs::Parenthesized { inputs, output } => {
+                let output = output.as_ref().map(|output| quote! { -> #output });
+                tokens.extend(quote! {
+                    ( #(#inputs),* ) #output
                 });
             }
         }
     }
 }
 
+impl<'input> ToTokens for AstAttribute<'input> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let code = TokenStream2::from_str(self.code).unwrap();
+        tokens.extend(quote! { #[#code] });
+    }
+}
+
+impl<'input> ToTokens for AstGenericParam<'input> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(generic_name_tokens(self.name));
+
+        if !self.bounds.is_empty() {
+            let bounds = &self.bounds;
+            tokens.extend(quote! { : #(#bounds)+* });
+        }
+    }
+}
+
+impl<'input> ToTokens for AstWherePredicate<'input> {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let ty = &self.ty;
+        let bounds = &self.bounds;
+        tokens.extend(quote! { #ty: #(#bounds)+* });
+    }
+}
+
 impl<'input> ToTokens for AstTypePath<'input> {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         if self.is_absolute {
             tokens.extend(quote! { :: });
         }
 
-        let segments = self
-            .segments
-            .iter()
-            .map(|segment| Ident::new(*segment, Span::call_site()));
+        let segments = self.segments.iter().map(|segment| rust_ident(segment));
         tokens.extend(quote! {
             #(#segments)::*
         });
     }
 }
+
+impl<'input> AstTokenPattern<'input> {
+    /// Generate the Rust pattern. If `bind_payloads` is true, the payloads are
+    /// bound to `__payload0`, `__payload1`, etc. Otherwise they are ignored.
+    fn to_pattern_tokens(&self, bind_payloads: bool) -> TokenStream2 {
+        self.write_pattern(bind_payloads, true, &mut 0)
+    }
+
+    fn write_pattern(
+        &self,
+        bind_payloads: bool,
+        is_top_level: bool,
+        next_payload: &mut usize,
+    ) -> TokenStream2 {
+        let mut write_all = |patterns: &[AstTokenPattern]| -> Vec<_> {
+            patterns
+                .iter()
+                .map(|pattern| pattern.write_pattern(bind_payloads, false, next_payload))
+                .collect()
+        };
+
+        match self {
+            // Variants with fields need to be matched with a struct pattern. This
+            // pattern also works for unit and tuple variants.
+            AstTokenPattern::Path(path) if is_top_level => quote! { #path { .. } },
+            AstTokenPattern::Path(path) => quote! { #path },
+            AstTokenPattern::TupleStruct(path, patterns) => {
+                let patterns = write_all(patterns);
+                quote! { #path(#(#patterns),*) }
+            }
+            AstTokenPattern::Struct(path, fields) => {
+                let fields = fields.iter().map(|field| match field {
+                    AstFieldPattern::Field(name, pattern) => {
+                        let name = rust_ident(name);
+                        let pattern = pattern.write_pattern(bind_payloads, false, next_payload);
+                        quote! { #name: #pattern }
+                    }
+                    AstFieldPattern::Rest => quote! { .. },
+                });
+                let fields: Vec<_> = fields.collect();
+                quote! { #path { #(#fields),* } }
+            }
+            AstTokenPattern::Tuple(patterns) => {
+                let patterns = write_all(patterns);
+                quote! { (#(#patterns,)*) }
+            }
+            AstTokenPattern::Literal(literal) => TokenStream2::from_str(literal).unwrap(),
+            AstTokenPattern::Wildcard => quote! { _ },
+            AstTokenPattern::Rest => quote! { .. },
+            AstTokenPattern::Payload(_) if bind_payloads => {
+                let payload = format_ident!("__payload{}", next_payload);
+                *next_payload += 1;
+                quote! { #payload }
+            }
+            AstTokenPattern::Payload(_) => quote! { _ },
+            AstTokenPattern::Or(alternatives) => {
+                // Each alternative binds the same payload names
+                let first_payload = *next_payload;
+                let mut alternative_patterns = Vec::new();
+
+                for alternative in alternatives {
+                    *next_payload = first_payload;
+                    alternative_patterns.push(alternative.write_pattern(
+                        bind_payloads,
+                        is_top_level,
+                        next_payload,
+                    ));
+                }
+
+                quote! { #(#alternative_patterns)|* }
+            }
+        }
+    }
+}
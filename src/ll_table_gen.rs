@@ -1,15 +1,24 @@
 //! LL(1) action table generation
 
-use crate::ast::{AstGrammar, AstNonterminal, AstProduction, AstSymbol};
+use crate::ast::{
+    AstAction, AstAttribute, AstFold, AstGenericArgs, AstGrammar, AstLocation, AstNonterminal,
+    AstPathSegment, AstProduction, AstQSelf, AstRepeatOp, AstSymbol, AstTypePath, AstTypeRef,
+};
+use crate::lexer::find_placeholders;
+use quote::ToTokens;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 pub(crate) type NullableMap<'input> = HashMap<&'input str, bool>;
 pub(crate) type FirstMap<'input> = HashMap<&'input str, HashSet<&'input str>>;
 pub(crate) type FollowMap<'input> = HashMap<&'input str, HashSet<&'input str>>;
+/// The productions to choose for each nonterminal and next terminal, in the
+/// order they're declared
 pub(crate) type ParseTable<'input> =
-    HashMap<(&'input str, &'input str), HashSet<AstProduction<'input>>>;
+    HashMap<(&'input str, &'input str), Vec<AstProduction<'input>>>;
 
-const WRAPPER_NONTERM: &str = "__ll_parser_wrapper_start";
+const WRAPPER_NONTERM_PREFIX: &str = "__ll_parser_wrapper_start";
 pub const EOF_TERMINAL: &str = "\"__ll_parser_eof\"";
 
 impl<'input> AstGrammar<'input> {
@@ -55,40 +64,1460 @@ impl<'input> AstGrammar<'input> {
                 .map(move |(i, production)| (nonterminal, production, i))
         })
     }
+
+    /// Get the Rust type of a symbol's value. Terminals produce the token
+    /// itself, or the token's payload if one is declared.
+    pub fn symbol_ty(&self, symbol: &str) -> Option<AstTypeRef<'input>> {
+        if symbol == EOF_TERMINAL {
+            return Some(AstTypeRef::Tuple(Vec::new()));
+        }
+
+        if let Some(token_alias) = self
+            .token_decl
+            .aliases
+            .iter()
+            .find(|alias| alias.term == symbol)
+        {
+            let mut payload_tys = token_alias.pattern.payload_tys();
+            return Some(match payload_tys.len() {
+                0 => self.token_decl.ty.clone(),
+                1 => payload_tys.remove(0).clone(),
+                _ => AstTypeRef::Tuple(payload_tys.into_iter().cloned().collect()),
+            });
+        }
+
+        self.nonterminals
+            .iter()
+            .find(|nonterminal| nonterminal.name == symbol)
+            .map(|nonterminal| nonterminal.ty.clone())
+    }
+
+    /// Get the Rust type of the locations given by `@L` and `@R`. These come
+    /// from the lexer if it's spanned, otherwise locations are the index of
+    /// the token in the input.
+    pub fn location_ty(&self) -> AstTypeRef<'input> {
+        self.extern_decl.location_ty.clone().unwrap_or_else(|| {
+            AstTypeRef::Ty(
+                AstTypePath {
+                    is_absolute: false,
+                    segments: vec!["usize"],
+                },
+                Vec::new(),
+            )
+        })
+    }
 }
 
 impl<'input> AstSymbol<'input> {
-    fn term_or_nonterm(&self) -> &'input str {
+    pub(crate) fn term_or_nonterm(&self) -> &'input str {
         match self {
-            AstSymbol::Terminal(term) => *term,
-            AstSymbol::Nonterminal(nonterm) => *nonterm,
-            AstSymbol::Named(_, nonterm) => *nonterm,
+            AstSymbol::Terminal(term) => term,
+            AstSymbol::Nonterminal(nonterm) | AstSymbol::Call(nonterm, _) => nonterm,
+            AstSymbol::Named(_, symbol) => symbol.term_or_nonterm(),
+            AstSymbol::Selected(_)
+            | AstSymbol::Repeat(_, _)
+            | AstSymbol::Group(_)
+            | AstSymbol::Macro(_, _)
+            | AstSymbol::Location(_)
+            | AstSymbol::Action(_, _)
+            | AstSymbol::Fold(_) => {
+                unreachable!("EBNF symbols and macros are lowered before table generation")
+            }
         }
     }
 }
 
-pub fn insert_wrapper_start_nonterm(ast: &mut AstGrammar) {
-    let start_nonterm = ast
-        .nonterminals
-        .iter_mut()
-        .find(|nonterminal| nonterminal.is_pub)
-        .expect("Must have a single public nonterminal");
-    start_nonterm.is_pub = false;
-
-    let wrapper_nonterm = AstNonterminal {
-        is_pub: true,
-        name: WRAPPER_NONTERM,
-        ty: start_nonterm.ty.clone(),
-        productions: vec![AstProduction {
-            symbols: vec![
-                AstSymbol::Named("result", start_nonterm.name),
-                AstSymbol::Terminal(EOF_TERMINAL),
+/// Displays the symbol like it's written in the grammar. This is used to name
+/// generated nonterminals.
+impl fmt::Display for AstSymbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AstSymbol::Terminal(name) | AstSymbol::Nonterminal(name) => f.write_str(name),
+            AstSymbol::Named(name, symbol) => write!(f, "<{}:{}>", name, symbol),
+            AstSymbol::Selected(symbol) => write!(f, "<{}>", symbol),
+            AstSymbol::Repeat(op, symbol) => write!(f, "{}{}", symbol, op),
+            AstSymbol::Group(alternatives) => {
+                f.write_str("(")?;
+                for (i, symbols) in alternatives.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" | ")?;
+                    }
+                    for (j, symbol) in symbols.iter().enumerate() {
+                        if j != 0 {
+                            f.write_str(" ")?;
+                        }
+                        write!(f, "{}", symbol)?;
+                    }
+                }
+                f.write_str(")")
+            }
+            AstSymbol::Macro(name, args) => {
+                write!(f, "{}<", name)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                f.write_str(">")
+            }
+            AstSymbol::Location(location) => write!(f, "{}", location),
+            AstSymbol::Call(name, args) => write!(f, "{}({})", name, args),
+            AstSymbol::Action(code, None) => write!(f, "{{ {} }}", code),
+            AstSymbol::Action(code, Some(ty)) => {
+                write!(f, "{{ {} }}: {}", code, ty.to_token_stream())
+            }
+            AstSymbol::Fold(fold) => {
+                let (arrow, body) = match fold.action {
+                    AstAction::FallibleCode(body) => ("=>?", body),
+                    AstAction::Code(body) => ("=>", body),
+                    _ => unreachable!("Folds have action code"),
+                };
+                write!(
+                    f,
+                    "fold({}, {} {} |{}, {}| {})",
+                    fold.init, fold.step, arrow, fold.params.0, fold.params.1, body
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for AstLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AstLocation::Start => "@L",
+            AstLocation::End => "@R",
+        })
+    }
+}
+
+impl fmt::Display for AstRepeatOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AstRepeatOp::ZeroOrMore => "*",
+            AstRepeatOp::OneOrMore => "+",
+            AstRepeatOp::Optional => "?",
+        })
+    }
+}
+
+/// Get a string which lives as long as the grammar, for the names of generated
+/// nonterminals and canonicalized terminals. Proc-macro expansion is
+/// short-lived, so the strings are leaked (once per unique name).
+pub(crate) fn intern(name: String) -> &'static str {
+    thread_local! {
+        static NAMES: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+    }
+
+    NAMES.with(|names| {
+        if let Some(interned) = names.borrow().get(name.as_str()) {
+            return *interned;
+        }
+
+        let interned: &'static str = Box::leak(name.into_boxed_str());
+        names.borrow_mut().insert(interned);
+        interned
+    })
+}
+
+/// Check that the payloads of each token alias can be unwrapped into one
+/// value, which isn't the case for an or-pattern whose alternatives bind
+/// different payload types
+pub fn check_token_patterns(ast: &AstGrammar) -> Result<(), String> {
+    for alias in &ast.token_decl.aliases {
+        if !alias.pattern.has_consistent_payloads() {
+            return Err(format!(
+                "The alternatives of the pattern for {} bind different payload types",
+                alias.term
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the extended BNF symbols (`Symbol*`, `Symbol+`, `Symbol?`, and
+/// parenthesized groups) and macro uses with generated nonterminals, so the
+/// rest of the table generation only has to deal with plain terminals and
+/// nonterminals.
+///
+/// `Symbol?` becomes a nonterminal producing an `Option`. `Symbol*` and
+/// `Symbol+` become a nonterminal which collects the values into a `Vec`, and
+/// a right-recursive "items" nonterminal which leaves each value on the stack
+/// until they are all collected.
+///
+/// Each use of a macro (`Comma<Expr>`) is expanded into a nonterminal named
+/// after the use, and the macro definitions are removed.
+///
+/// `@L` and `@R` become nonterminals with an empty production, which the parser
+/// reduces to the current location.
+///
+/// Mid-rule actions (`{ code }`) become nonterminals with an empty production,
+/// which the parser reduces by running the code.
+///
+/// `fold(...)` becomes a loop which folds each step into the value as soon as
+/// it's parsed, so the value is built left to right.
+///
+/// Unnamed selections (`<Symbol>`) are bound to generated names.
+///
+/// The nonterminals generated from the productions of a nonterminal with inputs
+/// can use its inputs, so they're named after it (`Symbol*@Declarator`) rather
+/// than shared with other nonterminals.
+pub fn lower_ebnf(ast: &mut AstGrammar) -> Result<(), String> {
+    let mut generated = Vec::new();
+    let user_defined = ast.nonterminals.len();
+
+    for nonterminal in 0..user_defined {
+        if !ast.nonterminals[nonterminal].params.is_empty() {
+            // Macros are lowered as part of their expansions
+            continue;
+        }
+
+        // Nonterminals with inputs don't share generated nonterminals, since theirs can
+        // use the inputs
+        let has_inputs = !ast.nonterminals[nonterminal].inputs.is_empty();
+        if has_inputs && ast.nonterminals[nonterminal].is_pub {
+            return Err(format!(
+                "{} takes inputs, so it can't be public",
+                ast.nonterminals[nonterminal].name
+            ));
+        }
+        let mut shared = if has_inputs {
+            ast.nonterminals.split_off(user_defined)
+        } else {
+            Vec::new()
+        };
+
+        for production in 0..ast.nonterminals[nonterminal].productions.len() {
+            let action = ast.nonterminals[nonterminal].productions[production]
+                .action
+                .clone();
+            let mut symbols =
+                std::mem::take(&mut ast.nonterminals[nonterminal].productions[production].symbols);
+
+            lower_production_symbols(ast, &mut symbols, &action, &mut generated)?;
+
+            ast.nonterminals[nonterminal].productions[production].symbols = symbols;
+        }
+
+        if has_inputs {
+            scope_generated_nonterminals(&mut ast.nonterminals[nonterminal], &mut generated);
+        }
+        ast.nonterminals.append(&mut shared);
+        ast.nonterminals.append(&mut generated);
+    }
+
+    propagate_resolve_attrs(ast, user_defined)?;
+    ast.nonterminals
+        .retain(|nonterminal| nonterminal.params.is_empty());
+    Ok(())
+}
+
+/// Pass `#[resolve(...)]` attributes on nonterminals and productions down to
+/// the nonterminals generated from them, since that's where conflicts like the
+/// optional `else` branch show up. Generated nonterminals which are also used
+/// by other productions are shared with them, so they're left alone.
+fn propagate_resolve_attrs(ast: &mut AstGrammar, user_defined: usize) -> Result<(), String> {
+    // The attributes are checked even if there's no conflict to resolve
+    for nonterminal in &ast.nonterminals[..user_defined] {
+        let attrs = nonterminal
+            .productions
+            .iter()
+            .flat_map(|production| &production.attrs)
+            .chain(&nonterminal.attrs);
+        for attr in attrs.filter(|attr| attr.name == "resolve") {
+            parse_resolve_attr(attr.code)?;
+        }
+    }
+
+    // The productions each attribute applies to, as nonterminal and production
+    // indices
+    let mut annotated: Vec<(AstAttribute, HashSet<(usize, usize)>)> = Vec::new();
+    for (i, nonterminal) in ast.nonterminals[..user_defined].iter().enumerate() {
+        if !nonterminal.params.is_empty() {
+            continue;
+        }
+
+        let nonterminal_attr = resolve_attr(&nonterminal.attrs);
+        for (j, production) in nonterminal.productions.iter().enumerate() {
+            let attr = match resolve_attr(&production.attrs).or(nonterminal_attr) {
+                Some(attr) => attr,
+                None => continue,
+            };
+
+            match annotated.iter_mut().find(|(other, _)| other == attr) {
+                Some((_, productions)) => {
+                    productions.insert((i, j));
+                }
+                None => annotated.push((attr.clone(), HashSet::from([(i, j)]))),
+            }
+        }
+    }
+
+    for (attr, productions) in annotated {
+        for i in exclusively_generated(ast, user_defined, &productions) {
+            let generated = &mut ast.nonterminals[i];
+            if resolve_attr(&generated.attrs).is_none() {
+                generated.attrs.push(attr.clone());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Find the generated nonterminals which are only used by the given
+/// productions, directly or through other such nonterminals. Returns their
+/// indices.
+fn exclusively_generated(
+    ast: &AstGrammar,
+    user_defined: usize,
+    productions: &HashSet<(usize, usize)>,
+) -> HashSet<usize> {
+    let used_generated = |i: usize, j: usize| {
+        ast.nonterminals[i].productions[j]
+            .symbols
+            .iter()
+            .filter_map(move |symbol| {
+                let name = symbol.term_or_nonterm();
+                ast.nonterminals[user_defined..]
+                    .iter()
+                    .position(|nonterminal| nonterminal.name == name)
+                    .map(|position| user_defined + position)
+            })
+    };
+
+    // Start from everything generated which the productions use
+    let mut exclusive = HashSet::new();
+    let mut pending: Vec<_> = productions.iter().copied().collect();
+    while let Some((i, j)) = pending.pop() {
+        for used in used_generated(i, j) {
+            if exclusive.insert(used) {
+                pending.extend((0..ast.nonterminals[used].productions.len()).map(|j| (used, j)));
+            }
+        }
+    }
+
+    // Then drop the ones which are also used elsewhere, until only the
+    // exclusive ones are left
+    loop {
+        let shared: Vec<_> = ast
+            .nonterminals
+            .iter()
+            .enumerate()
+            .flat_map(|(i, nonterminal)| (0..nonterminal.productions.len()).map(move |j| (i, j)))
+            .filter(|(i, j)| !productions.contains(&(*i, *j)) && !exclusive.contains(i))
+            .flat_map(|(i, j)| used_generated(i, j))
+            .filter(|used| exclusive.contains(used))
+            .collect();
+        if shared.is_empty() {
+            return exclusive;
+        }
+
+        for used in shared {
+            exclusive.remove(&used);
+        }
+    }
+}
+
+/// Rename the nonterminals generated from the productions of a nonterminal with
+/// inputs after it, and give them access to its inputs
+fn scope_generated_nonterminals<'input>(
+    owner: &mut AstNonterminal<'input>,
+    generated: &mut [AstNonterminal<'input>],
+) {
+    let renames: HashMap<_, _> = generated
+        .iter()
+        .map(|nonterminal| {
+            let name = intern(format!("{}@{}", nonterminal.name, owner.name));
+            (nonterminal.name, name)
+        })
+        .collect();
+
+    for production in &mut owner.productions {
+        rename_production(production, &renames);
+    }
+
+    for nonterminal in generated {
+        nonterminal.name = renames[nonterminal.name];
+        nonterminal.input_scope = Some(owner.name);
+
+        for production in &mut nonterminal.productions {
+            rename_production(production, &renames);
+        }
+    }
+}
+
+fn rename_production<'input>(
+    production: &mut AstProduction<'input>,
+    renames: &HashMap<&str, &'input str>,
+) {
+    for symbol in &mut production.symbols {
+        rename_symbol(symbol, renames);
+    }
+
+    match &mut production.action {
+        AstAction::Collect(element) => *element = renames.get(element).unwrap_or(element),
+        AstAction::Fold(target, folded) => {
+            *target = renames.get(target).unwrap_or(target);
+            rename_production(folded, renames);
+        }
+        _ => {}
+    }
+}
+
+/// Rename a lowered symbol
+fn rename_symbol<'input>(symbol: &mut AstSymbol<'input>, renames: &HashMap<&str, &'input str>) {
+    match symbol {
+        AstSymbol::Nonterminal(name) => *name = renames.get(name).unwrap_or(name),
+        AstSymbol::Named(_, inner) => rename_symbol(inner, renames),
+        _ => {}
+    }
+}
+
+fn lower_production_symbols<'input>(
+    ast: &AstGrammar<'input>,
+    symbols: &mut [AstSymbol<'input>],
+    action: &AstAction,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<(), String> {
+    let selects_all = match action {
+        AstAction::Implicit => true,
+        AstAction::Code(code) | AstAction::FallibleCode(code) => {
+            !find_placeholders(code).is_empty()
+        }
+        AstAction::Location(_)
+        | AstAction::Collect(_)
+        | AstAction::Passthrough
+        | AstAction::Fold(_, _) => false,
+    };
+    name_selections(symbols, selects_all);
+
+    for symbol in symbols {
+        lower_symbol(ast, symbol, generated)?;
+    }
+    Ok(())
+}
+
+/// Give the selected symbols (`<Symbol>`) a name based on their position, so
+/// they're passed to the action like named symbols. If `selects_all` is set
+/// and nothing is selected, every symbol is named.
+fn name_selections(symbols: &mut [AstSymbol], selects_all: bool) {
+    let any_selected = symbols
+        .iter()
+        .any(|symbol| matches!(symbol, AstSymbol::Named(_, _) | AstSymbol::Selected(_)));
+
+    for (i, symbol) in symbols.iter_mut().enumerate() {
+        let inner = match symbol {
+            AstSymbol::Selected(inner) => *inner.clone(),
+            AstSymbol::Named(_, _) => continue,
+            // Mid-rule actions are only passed on when they're bound
+            AstSymbol::Action(_, _) => continue,
+            _ if selects_all && !any_selected => symbol.clone(),
+            _ => continue,
+        };
+
+        *symbol = AstSymbol::Named(intern(format!("__{}", i)), Box::new(inner));
+    }
+}
+
+fn lower_symbol<'input>(
+    ast: &AstGrammar<'input>,
+    symbol: &mut AstSymbol<'input>,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<(), String> {
+    let nonterminal = match symbol {
+        AstSymbol::Terminal(_) => return Ok(()),
+        // Nonterminals with inputs have to be given their arguments
+        AstSymbol::Nonterminal(name) if input_count(ast, name) > 0 => {
+            return Err(format!(
+                "{} takes inputs, so it has to be used with arguments",
+                name
+            ))
+        }
+        AstSymbol::Call(name, _) if input_count(ast, name) == 0 => {
+            return Err(format!("{} doesn't take inputs", name))
+        }
+        AstSymbol::Nonterminal(_) | AstSymbol::Call(_, _) => return Ok(()),
+        AstSymbol::Named(_, inner) => return lower_symbol(ast, inner, generated),
+        AstSymbol::Selected(_) => unreachable!("Selections are named before lowering"),
+        AstSymbol::Repeat(op, inner) => {
+            lower_symbol(ast, inner, generated)?;
+            lower_repeat(ast, *op, inner, generated)?
+        }
+        AstSymbol::Group(alternatives) => {
+            for symbols in alternatives.iter_mut() {
+                lower_production_symbols(ast, symbols, &AstAction::Passthrough, generated)?;
+            }
+            lower_group(ast, alternatives, generated)?
+        }
+        AstSymbol::Macro(name, args) => {
+            for arg in args.iter_mut() {
+                lower_symbol(ast, arg, generated)?;
+            }
+            expand_macro(ast, name, args, generated)?
+        }
+        AstSymbol::Location(location) => lower_location(ast, *location, generated),
+        AstSymbol::Action(code, ty) => lower_mid_rule_action(ast, code, ty.as_ref(), generated),
+        AstSymbol::Fold(fold) => {
+            // The initial value is always passed on, so selecting it is a no-op
+            if let AstSymbol::Selected(inner) = &fold.init {
+                fold.init = *inner.clone();
+            }
+            lower_symbol(ast, &mut fold.init, generated)?;
+            // Every symbol of the step is passed to the closure, unless some
+            // are selected
+            if let AstSymbol::Group(alternatives) = &mut fold.step {
+                for symbols in alternatives.iter_mut() {
+                    name_selections(symbols, true);
+                }
+            }
+            lower_symbol(ast, &mut fold.step, generated)?;
+            lower_fold(ast, fold, generated)?
+        }
+    };
+
+    *symbol = AstSymbol::Nonterminal(nonterminal);
+    Ok(())
+}
+
+/// Generate the nonterminals for a repeated symbol, returning the name of the
+/// nonterminal which replaces it.
+fn lower_repeat<'input>(
+    ast: &AstGrammar<'input>,
+    op: AstRepeatOp,
+    inner: &AstSymbol<'input>,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<&'input str, String> {
+    let name = intern(format!("{}{}", inner, op));
+    if is_defined(ast, generated, name) {
+        return Ok(name);
+    }
+
+    let element_ty = lowered_symbol_ty(ast, generated, inner)?;
+
+    if op == AstRepeatOp::Optional {
+        generated.push(AstNonterminal {
+            docs: Vec::new(),
+            attrs: Vec::new(),
+            is_pub: false,
+            name,
+            params: Vec::new(),
+            inputs: Vec::new(),
+            input_scope: None,
+            ty: AstTypeRef::Ty(std_path("option", "Option"), vec![element_ty]),
+            productions: vec![
+                AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![AstSymbol::Named("__value", Box::new(inner.clone()))],
+                    action: AstAction::Code("Some(__value)"),
+                },
+                AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: Vec::new(),
+                    action: AstAction::Code("None"),
+                },
+            ],
+        });
+        return Ok(name);
+    }
+
+    let vec_ty = AstTypeRef::Ty(std_path("vec", "Vec"), vec![element_ty]);
+    let items_name = intern(format!("{}*_items", inner));
+    if !is_defined(ast, generated, items_name) {
+        generated.push(AstNonterminal {
+            docs: Vec::new(),
+            attrs: Vec::new(),
+            is_pub: false,
+            name: items_name,
+            params: Vec::new(),
+            inputs: Vec::new(),
+            input_scope: None,
+            ty: vec_ty.clone(),
+            productions: vec![
+                AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: vec![inner.clone(), AstSymbol::Nonterminal(items_name)],
+                    action: AstAction::Passthrough,
+                },
+                AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: Vec::new(),
+                    action: AstAction::Passthrough,
+                },
             ],
-            code: "result",
+        });
+    }
+
+    let mut symbols = vec![AstSymbol::Nonterminal(items_name)];
+    if op == AstRepeatOp::OneOrMore {
+        symbols.insert(0, inner.clone());
+    }
+
+    generated.push(AstNonterminal {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        is_pub: false,
+        name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: vec_ty,
+        productions: vec![AstProduction {
+            docs: Vec::new(),
+            attrs: Vec::new(),
+            predicates: Vec::new(),
+            symbols,
+            action: AstAction::Collect(inner.term_or_nonterm()),
         }],
+    });
+    Ok(name)
+}
+
+/// Generate the nonterminal for a parenthesized group, returning its name.
+/// Each alternative produces its named values (as a tuple if there are
+/// several), or the value of its only symbol if nothing is named. Every
+/// alternative has to produce the same type.
+fn lower_group<'input>(
+    ast: &AstGrammar<'input>,
+    alternatives: &[Vec<AstSymbol<'input>>],
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<&'input str, String> {
+    let name = intern(AstSymbol::Group(alternatives.to_vec()).to_string());
+    if is_defined(ast, generated, name) {
+        return Ok(name);
+    }
+
+    let mut group_ty: Option<AstTypeRef> = None;
+    let productions = alternatives
+        .iter()
+        .map(|symbols| {
+            let mut names = Vec::new();
+            let mut tys = Vec::new();
+            for symbol in symbols {
+                if let AstSymbol::Named(name, inner) = symbol {
+                    names.push(*name);
+                    tys.push(lowered_symbol_ty(ast, generated, inner)?);
+                }
+            }
+
+            let (symbols, code, ty) = match (names.len(), symbols.as_slice()) {
+                (0, [symbol]) => (
+                    vec![AstSymbol::Named("__value", Box::new(symbol.clone()))],
+                    "__value",
+                    lowered_symbol_ty(ast, generated, symbol)?,
+                ),
+                (0, _) => (symbols.clone(), "()", AstTypeRef::Tuple(Vec::new())),
+                (1, _) => (symbols.clone(), names[0], tys.into_iter().next().unwrap()),
+                _ => (
+                    symbols.clone(),
+                    intern(format!("({})", names.join(", "))),
+                    AstTypeRef::Tuple(tys),
+                ),
+            };
+
+            match &group_ty {
+                Some(group_ty) if *group_ty != ty => {
+                    return Err(format!(
+                        "The alternatives of {} have different types: {} and {}",
+                        name,
+                        group_ty.to_token_stream(),
+                        ty.to_token_stream()
+                    ))
+                }
+                Some(_) => {}
+                None => group_ty = Some(ty),
+            }
+            Ok(AstProduction {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                predicates: Vec::new(),
+                symbols,
+                action: AstAction::Code(code),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    generated.push(AstNonterminal {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        is_pub: false,
+        name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: group_ty.expect("Groups have at least one alternative"),
+        productions,
+    });
+    Ok(name)
+}
+
+/// Generate the nonterminal for a location pseudo-symbol, returning its name
+fn lower_location<'input>(
+    ast: &AstGrammar<'input>,
+    location: AstLocation,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> &'input str {
+    let name = intern(location.to_string());
+    if is_defined(ast, generated, name) {
+        return name;
+    }
+
+    generated.push(AstNonterminal {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        is_pub: false,
+        name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: ast.location_ty(),
+        productions: vec![AstProduction {
+            docs: Vec::new(),
+            attrs: Vec::new(),
+            predicates: Vec::new(),
+            symbols: Vec::new(),
+            action: AstAction::Location(location),
+        }],
+    });
+    name
+}
+
+/// Generate the nonterminal for a mid-rule action, returning its name. It has
+/// an empty production which runs the code, so the code runs as soon as the
+/// parser reaches it.
+fn lower_mid_rule_action<'input>(
+    ast: &AstGrammar<'input>,
+    code: &'input str,
+    ty: Option<&AstTypeRef<'input>>,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> &'input str {
+    let name = intern(AstSymbol::Action(code, ty.cloned()).to_string());
+    if is_defined(ast, generated, name) {
+        return name;
+    }
+
+    generated.push(AstNonterminal {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        is_pub: false,
+        name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: ty.cloned().unwrap_or_else(|| AstTypeRef::Tuple(Vec::new())),
+        productions: vec![AstProduction {
+            docs: Vec::new(),
+            attrs: Vec::new(),
+            predicates: Vec::new(),
+            symbols: Vec::new(),
+            action: AstAction::Code(code),
+        }],
+    });
+    name
+}
+
+/// Generate the nonterminals for a fold, returning the name of the one which
+/// replaces it. The steps are parsed in a loop after the initial value, and
+/// each one is folded into the value as soon as it's parsed:
+///
+/// ```text
+/// F = Init F#init F~
+/// F~ = Step F#step F~ | ()
+/// ```
+fn lower_fold<'input>(
+    ast: &AstGrammar<'input>,
+    fold: &AstFold<'input>,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<&'input str, String> {
+    let name = intern(AstSymbol::Fold(Box::new(fold.clone())).to_string());
+    if is_defined(ast, generated, name) {
+        return Ok(name);
+    }
+
+    let ty = lowered_symbol_ty(ast, generated, &fold.init)?;
+    let init_name = intern(format!("{}#init", name));
+    let step_name = intern(format!("{}#step", name));
+    let loop_name = intern(format!("{}~", name));
+
+    // The closure's parameters are bound to the values in the action
+    let (acc, rhs) = fold.params;
+    let code = |body| {
+        intern(format!(
+            "let {} = __acc; let {} = __step; {}",
+            acc, rhs, body
+        ))
+    };
+    let action = match fold.action {
+        AstAction::Code(body) => AstAction::Code(code(body)),
+        AstAction::FallibleCode(body) => AstAction::FallibleCode(code(body)),
+        _ => unreachable!("Folds have action code"),
+    };
+
+    let new_nonterminal = |name, productions| AstNonterminal {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        is_pub: false,
+        name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: ty.clone(),
+        productions,
+    };
+    let production = |symbols, action| AstProduction {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        predicates: Vec::new(),
+        symbols,
+        action,
+    };
+    let fold_into = |symbols, action| {
+        production(
+            Vec::new(),
+            AstAction::Fold(name, Box::new(production(symbols, action))),
+        )
     };
 
-    ast.nonterminals.push(wrapper_nonterm);
+    generated.extend([
+        new_nonterminal(
+            name,
+            vec![production(
+                vec![
+                    fold.init.clone(),
+                    AstSymbol::Nonterminal(init_name),
+                    AstSymbol::Nonterminal(loop_name),
+                ],
+                AstAction::Passthrough,
+            )],
+        ),
+        new_nonterminal(
+            loop_name,
+            vec![
+                production(
+                    vec![
+                        fold.step.clone(),
+                        AstSymbol::Nonterminal(step_name),
+                        AstSymbol::Nonterminal(loop_name),
+                    ],
+                    AstAction::Passthrough,
+                ),
+                production(Vec::new(), AstAction::Passthrough),
+            ],
+        ),
+        // The initial value becomes the first value of the fold
+        new_nonterminal(
+            init_name,
+            vec![fold_into(
+                vec![AstSymbol::Named("__value", Box::new(fold.init.clone()))],
+                AstAction::Code("__value"),
+            )],
+        ),
+        new_nonterminal(
+            step_name,
+            vec![fold_into(
+                vec![
+                    AstSymbol::Named("__acc", Box::new(AstSymbol::Nonterminal(name))),
+                    AstSymbol::Named("__step", Box::new(fold.step.clone())),
+                ],
+                action,
+            )],
+        ),
+    ]);
+    Ok(name)
+}
+
+/// Generate the nonterminal for a use of a macro, returning its name. The
+/// macro's parameters are replaced by the arguments in its type and
+/// productions.
+fn expand_macro<'input>(
+    ast: &AstGrammar<'input>,
+    name: &'input str,
+    args: &[AstSymbol<'input>],
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<&'input str, String> {
+    let expansion_name = intern(AstSymbol::Macro(name, args.to_vec()).to_string());
+    if is_defined(ast, generated, expansion_name) {
+        return Ok(expansion_name);
+    }
+
+    let definition = ast
+        .nonterminals
+        .iter()
+        .find(|nonterminal| nonterminal.name == name && !nonterminal.params.is_empty())
+        .ok_or_else(|| format!("Unknown macro {}", name))?;
+    if definition.params.len() != args.len() {
+        return Err(format!(
+            "Wrong number of arguments in {}: {} takes {}",
+            expansion_name,
+            name,
+            definition.params.len()
+        ));
+    }
+
+    let symbols: HashMap<_, _> = definition.params.iter().copied().zip(args).collect();
+    let tys: HashMap<_, _> = definition
+        .params
+        .iter()
+        .zip(args)
+        .map(|(param, arg)| Ok((*param, lowered_symbol_ty(ast, generated, arg)?)))
+        .collect::<Result<_, String>>()?;
+
+    // Add the expansion before lowering its productions, so recursive uses of
+    // the macro refer to it
+    let index = generated.len();
+    generated.push(AstNonterminal {
+        docs: Vec::new(),
+        attrs: definition.attrs.clone(),
+        is_pub: false,
+        name: expansion_name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: substitute_ty(&definition.ty, &tys),
+        productions: Vec::new(),
+    });
+
+    let mut productions: Vec<_> = definition
+        .productions
+        .iter()
+        .map(|production| AstProduction {
+            docs: Vec::new(),
+            attrs: production.attrs.clone(),
+            predicates: production.predicates.clone(),
+            symbols: production
+                .symbols
+                .iter()
+                .map(|symbol| substitute_symbol(symbol, &symbols))
+                .collect(),
+            action: production.action.clone(),
+        })
+        .collect();
+    for production in &mut productions {
+        lower_production_symbols(ast, &mut production.symbols, &production.action, generated)?;
+    }
+
+    generated[index].productions = productions;
+    Ok(expansion_name)
+}
+
+/// Replace macro parameters in a symbol with their arguments
+fn substitute_symbol<'input>(
+    symbol: &AstSymbol<'input>,
+    args: &HashMap<&str, &AstSymbol<'input>>,
+) -> AstSymbol<'input> {
+    let substitute_all = |symbols: &[AstSymbol<'input>]| {
+        symbols
+            .iter()
+            .map(|symbol| substitute_symbol(symbol, args))
+            .collect()
+    };
+
+    match symbol {
+        AstSymbol::Nonterminal(name) => args.get(name).map_or(symbol.clone(), |arg| (*arg).clone()),
+        AstSymbol::Terminal(_)
+        | AstSymbol::Location(_)
+        | AstSymbol::Action(_, _)
+        | AstSymbol::Call(_, _) => symbol.clone(),
+        AstSymbol::Named(name, inner) => {
+            AstSymbol::Named(name, Box::new(substitute_symbol(inner, args)))
+        }
+        AstSymbol::Selected(inner) => AstSymbol::Selected(Box::new(substitute_symbol(inner, args))),
+        AstSymbol::Repeat(op, inner) => {
+            AstSymbol::Repeat(*op, Box::new(substitute_symbol(inner, args)))
+        }
+        AstSymbol::Group(alternatives) => AstSymbol::Group(
+            alternatives
+                .iter()
+                .map(|symbols| substitute_all(symbols))
+                .collect(),
+        ),
+        AstSymbol::Macro(name, macro_args) => AstSymbol::Macro(name, substitute_all(macro_args)),
+        AstSymbol::Fold(fold) => AstSymbol::Fold(Box::new(AstFold {
+            init: substitute_symbol(&fold.init, args),
+            step: substitute_symbol(&fold.step, args),
+            ..(**fold).clone()
+        })),
+    }
+}
+
+/// Replace macro parameters in a type with the types of their arguments
+fn substitute_ty<'input>(
+    ty: &AstTypeRef<'input>,
+    args: &HashMap<&str, AstTypeRef<'input>>,
+) -> AstTypeRef<'input> {
+    match ty {
+        AstTypeRef::Ty(path, generics) => {
+            if let (false, [name], true) = (
+                path.is_absolute,
+                path.segments.as_slice(),
+                generics.is_empty(),
+            ) {
+                if let Some(arg) = args.get(name) {
+                    return arg.clone();
+                }
+            }
+
+            AstTypeRef::Ty(path.clone(), substitute_tys(generics, args))
+        }
+        AstTypeRef::Path {
+            qself,
+            is_absolute,
+            segments,
+        } => AstTypeRef::Path {
+            qself: qself.as_ref().map(|qself| AstQSelf {
+                ty: Box::new(substitute_ty(&qself.ty, args)),
+                as_trait: qself
+                    .as_trait
+                    .as_ref()
+                    .map(|as_trait| Box::new(substitute_ty(as_trait, args))),
+            }),
+            is_absolute: *is_absolute,
+            segments: segments
+                .iter()
+                .map(|segment| AstPathSegment {
+                    name: segment.name,
+                    args: match &segment.args {
+                        AstGenericArgs::None => AstGenericArgs::None,
+                        AstGenericArgs::AngleBracketed(generics) => {
+                            AstGenericArgs::AngleBracketed(substitute_tys(generics, args))
+                        }
+                        AstGenericArgs::Parenthesized { inputs, output } => {
+                            AstGenericArgs::Parenthesized {
+                                inputs: substitute_tys(inputs, args),
+                                output: output
+                                    .as_ref()
+                                    .map(|output| Box::new(substitute_ty(output, args))),
+                            }
+                        }
+                    },
+                })
+                .collect(),
+        },
+        AstTypeRef::Tuple(tys) => AstTypeRef::Tuple(substitute_tys(tys, args)),
+        AstTypeRef::Ref {
+            lifetime,
+            is_mut,
+            ty,
+        } => AstTypeRef::Ref {
+            lifetime: *lifetime,
+            is_mut: *is_mut,
+            ty: Box::new(substitute_ty(ty, args)),
+        },
+        AstTypeRef::Paren(ty) => AstTypeRef::Paren(Box::new(substitute_ty(ty, args))),
+        AstTypeRef::Slice(ty) => AstTypeRef::Slice(Box::new(substitute_ty(ty, args))),
+        AstTypeRef::Array(ty, len) => AstTypeRef::Array(Box::new(substitute_ty(ty, args)), len),
+        AstTypeRef::Ptr { is_mut, ty } => AstTypeRef::Ptr {
+            is_mut: *is_mut,
+            ty: Box::new(substitute_ty(ty, args)),
+        },
+        AstTypeRef::FnPtr {
+            is_unsafe,
+            abi,
+            params,
+            ret,
+        } => AstTypeRef::FnPtr {
+            is_unsafe: *is_unsafe,
+            abi: *abi,
+            params: substitute_tys(params, args),
+            ret: ret.as_ref().map(|ret| Box::new(substitute_ty(ret, args))),
+        },
+        AstTypeRef::DynTrait(bounds) => AstTypeRef::DynTrait(substitute_tys(bounds, args)),
+        AstTypeRef::ImplTrait(bounds) => AstTypeRef::ImplTrait(substitute_tys(bounds, args)),
+        AstTypeRef::AssocType(name, ty) => {
+            AstTypeRef::AssocType(name, Box::new(substitute_ty(ty, args)))
+        }
+        AstTypeRef::Never | AstTypeRef::Lifetime(_) => ty.clone(),
+    }
+}
+
+fn substitute_tys<'input>(
+    tys: &[AstTypeRef<'input>],
+    args: &HashMap<&str, AstTypeRef<'input>>,
+) -> Vec<AstTypeRef<'input>> {
+    tys.iter().map(|ty| substitute_ty(ty, args)).collect()
+}
+
+/// Get the type of a symbol which may refer to a generated nonterminal
+fn lowered_symbol_ty<'input>(
+    ast: &AstGrammar<'input>,
+    generated: &[AstNonterminal<'input>],
+    symbol: &AstSymbol<'input>,
+) -> Result<AstTypeRef<'input>, String> {
+    let name = symbol.term_or_nonterm();
+
+    ast.symbol_ty(name)
+        .or_else(|| {
+            generated
+                .iter()
+                .find(|nonterminal| nonterminal.name == name)
+                .map(|nonterminal| nonterminal.ty.clone())
+        })
+        .ok_or_else(|| format!("Unknown symbol {}", name))
+}
+
+/// Get the number of inputs of a nonterminal in the grammar
+fn input_count(ast: &AstGrammar, name: &str) -> usize {
+    ast.nonterminals
+        .iter()
+        .find(|nonterminal| nonterminal.name == name)
+        .map_or(0, |nonterminal| nonterminal.inputs.len())
+}
+
+/// Check if the nonterminal is in the grammar or was already generated
+fn is_defined(ast: &AstGrammar, generated: &[AstNonterminal], name: &str) -> bool {
+    ast.nonterminals()
+        .chain(generated.iter().map(|nonterminal| nonterminal.name))
+        .any(|nonterminal| nonterminal == name)
+}
+
+/// Get an absolute path to an item in the standard library
+fn std_path(module: &'static str, item: &'static str) -> AstTypePath<'static> {
+    AstTypePath {
+        is_absolute: true,
+        segments: vec!["std", module, item],
+    }
+}
+
+/// The associativity of the operators in a precedence level
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Assoc {
+    Left,
+    Right,
+    None,
+}
+
+/// The productions of a nonterminal at one precedence level, with their index
+/// in the nonterminal's production list
+struct PrecedenceLevel<'a, 'input> {
+    level: usize,
+    assoc: Assoc,
+    productions: Vec<(usize, &'a AstProduction<'input>)>,
+}
+
+/// Replace the nonterminals which declare operator precedence levels
+/// (`#[precedence(level = 1, assoc = "left")]`) with a nonterminal for each
+/// level. A level applies to its production and the ones after it, and lower
+/// levels bind tighter.
+///
+/// Each level parses its operands at the next lower level, and the operators
+/// in a loop after the first operand:
+///
+/// ```text
+/// Expr#1 = Expr#0 Expr#1~
+/// Expr#1~ = "+" Expr#0 Expr#fold2 Expr#1~ | ()
+/// ```
+///
+/// Each operator's production is reduced by a `Fold` as soon as its last
+/// operand is parsed, so left-associative operators combine their operands
+/// left to right. Right-associative levels parse the right operand at the same
+/// level instead of looping, and non-associative levels don't loop. Prefix
+/// operators are parsed at their own level, and productions which don't start
+/// or end with the nonterminal are the operands of the lowest level.
+pub fn lower_precedence(ast: &mut AstGrammar) -> Result<(), String> {
+    let mut generated = Vec::new();
+
+    for nonterminal in &mut ast.nonterminals {
+        let has_levels = nonterminal
+            .productions
+            .iter()
+            .any(|production| precedence_attr(production).is_some());
+
+        if has_levels {
+            if !nonterminal.inputs.is_empty() {
+                return Err(format!(
+                    "{} takes inputs, so it can't use precedence levels",
+                    nonterminal.name
+                ));
+            }
+            lower_precedence_levels(nonterminal, &mut generated)?;
+        }
+    }
+
+    ast.nonterminals.append(&mut generated);
+    Ok(())
+}
+
+fn lower_precedence_levels<'input>(
+    nonterminal: &mut AstNonterminal<'input>,
+    generated: &mut Vec<AstNonterminal<'input>>,
+) -> Result<(), String> {
+    let name = nonterminal.name;
+    let is_operand =
+        |symbol: Option<&AstSymbol>| symbol.is_some_and(|symbol| symbol.term_or_nonterm() == name);
+    let new_nonterminal = |name, productions| AstNonterminal {
+        docs: Vec::new(),
+        attrs: Vec::new(),
+        is_pub: false,
+        name,
+        params: Vec::new(),
+        inputs: Vec::new(),
+        input_scope: None,
+        ty: nonterminal.ty.clone(),
+        productions,
+    };
+    let passthrough = |symbols, attrs| AstProduction {
+        docs: Vec::new(),
+        attrs,
+        predicates: Vec::new(),
+        symbols,
+        action: AstAction::Passthrough,
+    };
+
+    // Group the productions by level, keeping their order within each level
+    let mut levels: Vec<PrecedenceLevel> = Vec::new();
+    let (mut level, mut assoc) = (0, Assoc::Left);
+    for (i, production) in nonterminal.productions.iter().enumerate() {
+        if let Some(attr) = precedence_attr(production) {
+            (level, assoc) = parse_precedence_attr(attr.code)?;
+        }
+
+        match levels.iter_mut().find(|other| other.level == level) {
+            Some(other) if other.assoc != assoc => {
+                return Err(format!(
+                    "Precedence level {} of {} is declared with different associativities",
+                    level, name
+                ))
+            }
+            Some(other) => other.productions.push((i, production)),
+            None => levels.push(PrecedenceLevel {
+                level,
+                assoc,
+                productions: vec![(i, production)],
+            }),
+        }
+    }
+    levels.sort_by_key(|level| level.level);
+
+    let atoms_name = intern(format!("{}#atom", name));
+    let mut atoms = Vec::new();
+    let mut operand = atoms_name;
+
+    for PrecedenceLevel {
+        level,
+        assoc,
+        productions,
+    } in levels
+    {
+        let level_name = intern(format!("{}#{}", name, level));
+        let loop_name = intern(format!("{}#{}~", name, level));
+        let mut level_productions = Vec::new();
+        let mut loop_productions = Vec::new();
+
+        for (i, production) in productions {
+            // The operator is reduced once its operands are parsed
+            let fold_name = intern(format!("{}#fold{}", name, i));
+            generated.push(AstNonterminal {
+                attrs: nonterminal.attrs.clone(),
+                ..new_nonterminal(
+                    fold_name,
+                    vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: Vec::new(),
+                        action: AstAction::Fold(name, Box::new(production.clone())),
+                    }],
+                )
+            });
+            let fold = AstSymbol::Nonterminal(fold_name);
+            let cfg_attrs: Vec<_> = production
+                .attrs
+                .iter()
+                .filter(|attr| attr.is_cfg())
+                .cloned()
+                .collect();
+            // The predicates are checked where the lowered production is chosen
+            let lowered = |symbols| AstProduction {
+                predicates: production.predicates.clone(),
+                ..passthrough(symbols, cfg_attrs.clone())
+            };
+
+            let symbols = &production.symbols;
+            let (first, last) = (symbols.first(), symbols.last());
+            match (is_operand(first), is_operand(last)) {
+                // Infix: the left operand is already parsed by the loop
+                (true, true) if symbols.len() > 1 => {
+                    let mut loop_symbols = symbols[1..(symbols.len() - 1)].to_vec();
+                    if assoc == Assoc::Right {
+                        loop_symbols.extend([AstSymbol::Nonterminal(level_name), fold]);
+                    } else {
+                        loop_symbols.extend([AstSymbol::Nonterminal(operand), fold]);
+                    }
+                    if assoc == Assoc::Left {
+                        loop_symbols.push(AstSymbol::Nonterminal(loop_name));
+                    }
+                    loop_productions.push(lowered(loop_symbols));
+                }
+                // Postfix
+                (true, false) => {
+                    let mut loop_symbols = symbols[1..].to_vec();
+                    loop_symbols.push(fold);
+                    if assoc != Assoc::None {
+                        loop_symbols.push(AstSymbol::Nonterminal(loop_name));
+                    }
+                    loop_productions.push(lowered(loop_symbols));
+                }
+                // Prefix: the operand can have more prefix operators
+                (false, true) => {
+                    let mut level_symbols = symbols[..(symbols.len() - 1)].to_vec();
+                    level_symbols.extend([AstSymbol::Nonterminal(level_name), fold]);
+                    level_productions.push(lowered(level_symbols));
+                }
+                _ => {
+                    let mut atom_symbols = symbols.clone();
+                    atom_symbols.push(fold);
+                    atoms.push(lowered(atom_symbols));
+                }
+            }
+        }
+
+        // A level of operands only doesn't need its own nonterminal
+        if level_productions.is_empty() && loop_productions.is_empty() {
+            continue;
+        }
+
+        if loop_productions.is_empty() {
+            level_productions.push(passthrough(
+                vec![AstSymbol::Nonterminal(operand)],
+                Vec::new(),
+            ));
+        } else {
+            level_productions.push(passthrough(
+                vec![
+                    AstSymbol::Nonterminal(operand),
+                    AstSymbol::Nonterminal(loop_name),
+                ],
+                Vec::new(),
+            ));
+            loop_productions.push(passthrough(Vec::new(), Vec::new()));
+            generated.push(new_nonterminal(loop_name, loop_productions));
+        }
+
+        generated.push(new_nonterminal(level_name, level_productions));
+        operand = level_name;
+    }
+
+    if atoms.is_empty() {
+        return Err(format!(
+            "{} has no operands: at least one production has to be neither infix, \
+             prefix nor postfix",
+            name
+        ));
+    }
+    generated.push(new_nonterminal(atoms_name, atoms));
+    nonterminal.productions = vec![passthrough(
+        vec![AstSymbol::Nonterminal(operand)],
+        Vec::new(),
+    )];
+    Ok(())
+}
+
+fn precedence_attr<'a, 'input>(
+    production: &'a AstProduction<'input>,
+) -> Option<&'a AstAttribute<'input>> {
+    production
+        .attrs
+        .iter()
+        .find(|attr| attr.name == "precedence")
+}
+
+/// Get the level and associativity from the contents of a precedence
+/// attribute (`precedence(level = 1, assoc = "left")`). The associativity
+/// defaults to left.
+fn parse_precedence_attr(code: &str) -> Result<(usize, Assoc), String> {
+    let args = code
+        .find('(')
+        .zip(code.rfind(')'))
+        .map(|(start, end)| &code[(start + 1)..end])
+        .ok_or_else(|| format!("Expected arguments in #[{}]", code))?;
+    let mut level = None;
+    let mut assoc = Assoc::Left;
+
+    for arg in args.split(',').filter(|arg| !arg.trim().is_empty()) {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| format!("Expected `key = value` in #[{}]", code))?;
+        let value = value.trim().trim_matches('"');
+
+        match key.trim() {
+            "level" => {
+                level = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("Invalid precedence level {}", value))?,
+                )
+            }
+            "assoc" => {
+                assoc = match value {
+                    "left" => Assoc::Left,
+                    "right" => Assoc::Right,
+                    "none" => Assoc::None,
+                    _ => return Err(format!("Invalid associativity {}", value)),
+                }
+            }
+            key => return Err(format!("Unknown precedence argument {}", key)),
+        }
+    }
+
+    let level = level.ok_or_else(|| format!("Expected a level in #[{}]", code))?;
+    Ok((level, assoc))
+}
+
+/// Wrap each public nonterminal in a new start nonterminal which expects EOF
+/// after it. The wrappers become the only public nonterminals, and each one is
+/// a separate entry point into the same parse table.
+pub fn insert_wrapper_start_nonterms(ast: &mut AstGrammar) {
+    let mut wrappers = Vec::new();
+
+    for start_nonterm in ast
+        .nonterminals
+        .iter_mut()
+        .filter(|nonterminal| nonterminal.is_pub)
+    {
+        start_nonterm.is_pub = false;
+
+        wrappers.push(AstNonterminal {
+            docs: start_nonterm.docs.clone(),
+            attrs: start_nonterm.attrs.clone(),
+            is_pub: true,
+            name: wrapper_nonterm(start_nonterm.name),
+            params: Vec::new(),
+            inputs: Vec::new(),
+            input_scope: None,
+            ty: start_nonterm.ty.clone(),
+            productions: vec![AstProduction {
+                docs: Vec::new(),
+                attrs: Vec::new(),
+                predicates: Vec::new(),
+                symbols: vec![
+                    AstSymbol::Named(
+                        "result",
+                        Box::new(AstSymbol::Nonterminal(start_nonterm.name)),
+                    ),
+                    AstSymbol::Terminal(EOF_TERMINAL),
+                ],
+                action: AstAction::Code("result"),
+            }],
+        });
+    }
+
+    assert!(!wrappers.is_empty(), "Must have a public nonterminal");
+    ast.nonterminals.append(&mut wrappers);
+}
+
+/// Get the name of the wrapper for a public nonterminal
+fn wrapper_nonterm(start_nonterm: &str) -> &'static str {
+    intern(format!("{}_{}", WRAPPER_NONTERM_PREFIX, start_nonterm))
 }
 
 pub fn compute_nullable<'input>(ast: &AstGrammar<'input>) -> NullableMap<'input> {
@@ -221,6 +1650,8 @@ pub fn compute_follow<'input>(
     follow
 }
 
+/// Compute the LL(1) parse table. A cell with several productions is a
+/// conflict, unless they're all guarded by predicates (see [`find_conflict`]).
 pub fn compute_parse_table<'input>(
     ast: &AstGrammar<'input>,
     nullable: &NullableMap<'input>,
@@ -228,39 +1659,154 @@ pub fn compute_parse_table<'input>(
     follow: &FollowMap<'input>,
 ) -> ParseTable<'input> {
     let terminals: Vec<_> = ast.terminals().collect();
-    let mut parse_table = HashMap::new();
+    let mut parse_table: ParseTable = HashMap::new();
 
     for nonterm in ast.nonterminals() {
         for term in &terminals {
-            parse_table.insert((nonterm, *term), HashSet::new());
+            parse_table.insert((nonterm, *term), Vec::new());
         }
     }
 
     for (nonterminal, production) in ast.productions() {
+        let mut terms = first_range(&production.symbols, first, nullable);
         if production
             .symbols
             .iter()
             .all(|symbol| nullable[symbol.term_or_nonterm()])
         {
-            for term in &follow[nonterminal] {
-                parse_table
-                    .get_mut(&(nonterminal, *term))
-                    .unwrap()
-                    .insert(production.clone());
-            }
+            terms.extend(&follow[nonterminal]);
         }
 
-        for term in first_range(&production.symbols, first, nullable) {
+        for term in terms {
             parse_table
                 .get_mut(&(nonterminal, term))
                 .unwrap()
-                .insert(production.clone());
+                .push(production.clone());
         }
     }
 
     parse_table
 }
 
+/// Find a parse-table cell with several productions to choose from. Productions
+/// which are all guarded by predicates are tried in order, so they don't
+/// conflict.
+pub fn find_conflict<'a, 'input>(
+    parse_table: &'a ParseTable<'input>,
+) -> Option<(
+    &'a (&'input str, &'input str),
+    &'a Vec<AstProduction<'input>>,
+)> {
+    parse_table.iter().find(|(_, productions)| {
+        productions.len() > 1
+            && !productions
+                .iter()
+                .all(|production| !production.predicates.is_empty())
+    })
+}
+
+/// Resolve the conflicts in the parse table which are annotated. A production
+/// marked `#[prefer]` wins the cells it conflicts in, and `#[resolve(shift)]`
+/// on a nonterminal or production prefers the productions which start with the
+/// next token over the ones which are empty. Returns a note for each resolved
+/// conflict.
+pub fn resolve_conflicts<'input>(
+    ast: &AstGrammar<'input>,
+    parse_table: &mut ParseTable<'input>,
+    nullable: &NullableMap<'input>,
+    first: &FirstMap<'input>,
+) -> Vec<String> {
+    let mut notes = Vec::new();
+
+    for ((nonterm, term), productions) in parse_table.iter_mut() {
+        let is_guarded = productions
+            .iter()
+            .all(|production| !production.predicates.is_empty());
+        if productions.len() < 2 || is_guarded {
+            continue;
+        }
+
+        let preferred: Vec<_> = productions
+            .iter()
+            .filter(|production| production.attrs.iter().any(|attr| attr.name == "prefer"))
+            .collect();
+        let resolve_attr = ast
+            .nonterminals
+            .iter()
+            .find(|nonterminal| nonterminal.name == *nonterm)
+            .and_then(|nonterminal| resolve_attr(&nonterminal.attrs))
+            .or_else(|| {
+                productions
+                    .iter()
+                    .find_map(|production| resolve_attr(&production.attrs))
+            });
+
+        let (winner, reason) = match (preferred.as_slice(), resolve_attr) {
+            ([production], _) => ((*production).clone(), "#[prefer]"),
+            (_, Some(_)) => {
+                let shifting: Vec<_> = productions
+                    .iter()
+                    .filter(|production| {
+                        first_range(&production.symbols, first, nullable).contains(term)
+                    })
+                    .collect();
+
+                match shifting.as_slice() {
+                    [production] => ((*production).clone(), "#[resolve(shift)]"),
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        notes.push(format!(
+            "Resolved a parse-table conflict at symbol \"{}\" and terminal {} with {}, choosing: {}",
+            nonterm,
+            term,
+            reason,
+            production_display(&winner)
+        ));
+        *productions = vec![winner];
+    }
+
+    // The notes are reported in a stable order
+    notes.sort();
+    notes
+}
+
+/// Get the `#[resolve(...)]` attribute of a nonterminal or production
+fn resolve_attr<'a, 'input>(attrs: &'a [AstAttribute<'input>]) -> Option<&'a AstAttribute<'input>> {
+    attrs.iter().find(|attr| attr.name == "resolve")
+}
+
+/// Check the contents of a resolve attribute. Only `resolve(shift)` is
+/// supported.
+fn parse_resolve_attr(code: &str) -> Result<(), String> {
+    let args = code
+        .find('(')
+        .zip(code.rfind(')'))
+        .map(|(start, end)| code[(start + 1)..end].trim());
+
+    if args != Some("shift") {
+        return Err(format!("Expected #[resolve(shift)], found #[{}]", code));
+    }
+    Ok(())
+}
+
+/// Show a production's symbols, for reporting it
+fn production_display(production: &AstProduction) -> String {
+    if production.symbols.is_empty() {
+        return "(empty)".to_string();
+    }
+
+    production
+        .symbols
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Compute the possible first terminals in a range of symbols
 fn first_range<'input>(
     symbols: &[AstSymbol<'input>],
@@ -289,11 +1835,11 @@ mod tests {
     macro_rules! collection {
         // map-like
         ($($k:expr => $v:expr),* $(,)?) => {
-            std::iter::Iterator::collect(std::array::IntoIter::new([$(($k, $v),)*]))
+            std::iter::Iterator::collect(IntoIterator::into_iter([$(($k, $v),)*]))
         };
         // set-like
         ($($v:expr),* $(,)?) => {
-            std::iter::Iterator::collect(std::array::IntoIter::new([$($v,)*]))
+            std::iter::Iterator::collect(IntoIterator::into_iter([$($v,)*]))
         };
     }
 
@@ -322,7 +1868,7 @@ mod tests {
             pub MyNonterminal: () = "a" => ();
             MyEmptyNonterminal: () = => ();
         };
-        insert_wrapper_start_nonterm(&mut ast);
+        insert_wrapper_start_nonterms(&mut ast);
 
         assert_eq!(
             compute_nullable(&ast),
@@ -330,12 +1876,514 @@ mod tests {
                 "MyNonterminal" => false,
                 "MyEmptyNonterminal" => true,
                 "\"a\"" => false,
-                WRAPPER_NONTERM => false,
+                wrapper_nonterm("MyNonterminal") => false,
                 EOF_TERMINAL => false,
             }
         );
     }
 
+    #[test]
+    fn mismatched_payloads() {
+        let ast = parse_grammar! {
+            token Token {
+                "NUMBER" = Token::Int(<i64>) | Token::Float(<f64>),
+                "SIGN" = Token::Sign('+' | '-'),
+                "PAIR" = Token::Pair(<u8>, _) | Token::Triple(<u8>, _, _)
+            }
+            grammar;
+        };
+
+        assert_eq!(
+            check_token_patterns(&ast),
+            Err(
+                "The alternatives of the pattern for \"NUMBER\" bind different payload types"
+                    .to_string()
+            )
+        );
+        assert!(!ast.token_decl.aliases[0].pattern.has_consistent_payloads());
+        assert!(ast.token_decl.aliases[1].pattern.has_consistent_payloads());
+        assert!(ast.token_decl.aliases[2].pattern.has_consistent_payloads());
+    }
+
+    #[test]
+    fn lower_repetitions() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "a" = Token::A,
+                "b" = Token::B(<u8>)
+            }
+            grammar;
+            pub Start: () = "a"* B+ "a"? => ();
+            B: u8 = <b:"b"> => b;
+        };
+        lower_ebnf(&mut ast).unwrap();
+
+        let vec_ty = |ty| AstTypeRef::Ty(std_path("vec", "Vec"), vec![ty]);
+        let token_ty = AstTypeRef::simple_ty(vec!["Token"]);
+        let u8_ty = AstTypeRef::simple_ty(vec!["u8"]);
+        assert_eq!(
+            ast.nonterminals[0].productions[0].symbols,
+            vec![
+                AstSymbol::Nonterminal("\"a\"*"),
+                AstSymbol::Nonterminal("B+"),
+                AstSymbol::Nonterminal("\"a\"?"),
+            ]
+        );
+        assert_eq!(
+            ast.nonterminals[2..],
+            [
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "\"a\"*_items",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: vec_ty(token_ty.clone()),
+                    productions: vec![
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![
+                                AstSymbol::Terminal("\"a\""),
+                                AstSymbol::Nonterminal("\"a\"*_items")
+                            ],
+                            action: AstAction::Passthrough
+                        },
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: symbols!(),
+                            action: AstAction::Passthrough
+                        },
+                    ]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "\"a\"*",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: vec_ty(token_ty.clone()),
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![AstSymbol::Nonterminal("\"a\"*_items")],
+                        action: AstAction::Collect("\"a\"")
+                    }]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "B*_items",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: vec_ty(u8_ty.clone()),
+                    productions: vec![
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![
+                                AstSymbol::Nonterminal("B"),
+                                AstSymbol::Nonterminal("B*_items")
+                            ],
+                            action: AstAction::Passthrough
+                        },
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: symbols!(),
+                            action: AstAction::Passthrough
+                        },
+                    ]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "B+",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: vec_ty(u8_ty),
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![
+                            AstSymbol::Nonterminal("B"),
+                            AstSymbol::Nonterminal("B*_items")
+                        ],
+                        action: AstAction::Collect("B")
+                    }]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "\"a\"?",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::Ty(std_path("option", "Option"), vec![token_ty]),
+                    productions: vec![
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![AstSymbol::Named(
+                                "__value",
+                                Box::new(AstSymbol::Terminal("\"a\""))
+                            )],
+                            action: AstAction::Code("Some(__value)")
+                        },
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: symbols!(),
+                            action: AstAction::Code("None")
+                        },
+                    ]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn lower_groups() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "+" = Token::Plus,
+                "-" = Token::Minus,
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = ("+" | "-") (<a:"n"> <b:"n"> | "-" <a:"n"> <b:"n">) (B | "-" <B>) => ();
+            B: u8 = "n" => 0;
+        };
+        lower_ebnf(&mut ast).unwrap();
+
+        let token_ty = AstTypeRef::simple_ty(vec!["Token"]);
+        let i32_ty = AstTypeRef::simple_ty(vec!["i32"]);
+        assert_eq!(
+            ast.nonterminals[0].productions[0].symbols,
+            vec![
+                AstSymbol::Nonterminal("(\"+\" | \"-\")"),
+                AstSymbol::Nonterminal("(<a:\"n\"> <b:\"n\"> | \"-\" <a:\"n\"> <b:\"n\">)"),
+                AstSymbol::Nonterminal("(B | \"-\" <__1:B>)"),
+            ]
+        );
+        assert_eq!(
+            ast.nonterminals[2..],
+            [
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "(\"+\" | \"-\")",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: token_ty,
+                    productions: vec![
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![AstSymbol::Named(
+                                "__value",
+                                Box::new(AstSymbol::Terminal("\"+\""))
+                            )],
+                            action: AstAction::Code("__value")
+                        },
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![AstSymbol::Named(
+                                "__value",
+                                Box::new(AstSymbol::Terminal("\"-\""))
+                            )],
+                            action: AstAction::Code("__value")
+                        },
+                    ]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "(<a:\"n\"> <b:\"n\"> | \"-\" <a:\"n\"> <b:\"n\">)",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::Tuple(vec![i32_ty.clone(), i32_ty]),
+                    productions: vec![
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![
+                                AstSymbol::Named("a", Box::new(AstSymbol::Terminal("\"n\""))),
+                                AstSymbol::Named("b", Box::new(AstSymbol::Terminal("\"n\""))),
+                            ],
+                            action: AstAction::Code("(a, b)")
+                        },
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![
+                                AstSymbol::Terminal("\"-\""),
+                                AstSymbol::Named("a", Box::new(AstSymbol::Terminal("\"n\""))),
+                                AstSymbol::Named("b", Box::new(AstSymbol::Terminal("\"n\""))),
+                            ],
+                            action: AstAction::Code("(a, b)")
+                        },
+                    ]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "(B | \"-\" <__1:B>)",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::simple_ty(vec!["u8"]),
+                    productions: vec![
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![AstSymbol::Named(
+                                "__value",
+                                Box::new(AstSymbol::Nonterminal("B"))
+                            )],
+                            action: AstAction::Code("__value")
+                        },
+                        AstProduction {
+                            docs: Vec::new(),
+                            attrs: Vec::new(),
+                            predicates: Vec::new(),
+                            symbols: vec![
+                                AstSymbol::Terminal("\"-\""),
+                                AstSymbol::Named("__1", Box::new(AstSymbol::Nonterminal("B"))),
+                            ],
+                            action: AstAction::Code("__1")
+                        },
+                    ]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatched_group_types() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = (<a:"n"> <b:"n"> | "n" "n") => ();
+        };
+
+        assert_eq!(
+            lower_ebnf(&mut ast),
+            Err(
+                "The alternatives of (<a:\"n\"> <b:\"n\"> | \"n\" \"n\") have different \
+                 types: (i32 , i32 ,) and ()"
+                    .to_string()
+            )
+        );
+
+        let mut ast = parse_grammar! {
+            token Token {
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = (B | ) => ();
+            B: u8 = "n" => 0;
+        };
+
+        assert_eq!(
+            lower_ebnf(&mut ast),
+            Err("The alternatives of (B | ) have different types: u8 and ()".to_string())
+        );
+    }
+
+    #[test]
+    fn expand_macros() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "," = Token::Comma,
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = Pair<"n", B> Pair<"n", B> => ();
+            Pair<X, Y>: (X, Y) = <x:X> "," <y:Y> => (x, y);
+            B: u8 = "n" => 0;
+        };
+        lower_ebnf(&mut ast).unwrap();
+
+        assert_eq!(
+            ast.nonterminals[0].productions[0].symbols,
+            vec![
+                AstSymbol::Nonterminal("Pair<\"n\", B>"),
+                AstSymbol::Nonterminal("Pair<\"n\", B>"),
+            ]
+        );
+        assert_eq!(
+            ast.nonterminals[1..],
+            [
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "B",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::simple_ty(vec!["u8"]),
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: symbols!("n"),
+                        action: AstAction::Code("0")
+                    }]
+                },
+                AstNonterminal {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    is_pub: false,
+                    name: "Pair<\"n\", B>",
+                    params: Vec::new(),
+                    inputs: Vec::new(),
+                    input_scope: None,
+                    ty: AstTypeRef::Tuple(vec![
+                        AstTypeRef::simple_ty(vec!["i32"]),
+                        AstTypeRef::simple_ty(vec!["u8"]),
+                    ]),
+                    productions: vec![AstProduction {
+                        docs: Vec::new(),
+                        attrs: Vec::new(),
+                        predicates: Vec::new(),
+                        symbols: vec![
+                            AstSymbol::Named("x", Box::new(AstSymbol::Terminal("\"n\""))),
+                            AstSymbol::Terminal("\",\""),
+                            AstSymbol::Named("y", Box::new(AstSymbol::Nonterminal("B"))),
+                        ],
+                        action: AstAction::Code("(x, y)")
+                    }]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_macro_uses() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = Pair<"n"> => ();
+            Pair<X, Y>: (X, Y) = <x:X> <y:Y> => (x, y);
+        };
+        assert_eq!(
+            lower_ebnf(&mut ast),
+            Err("Wrong number of arguments in Pair<\"n\">: Pair takes 2".to_string())
+        );
+
+        let mut ast = parse_grammar! {
+            token Token {
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = Triple<"n", "n", "n"> => ();
+        };
+        assert_eq!(
+            lower_ebnf(&mut ast),
+            Err("Unknown macro Triple".to_string())
+        );
+    }
+
+    #[test]
+    fn conflict_in_macro_expansion() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "n" = Token::Num(<i32>)
+            }
+            grammar;
+            pub Start: () = Twice<"n"> => ();
+            Twice<X>: () = {
+                X => (),
+                X X => (),
+            };
+        };
+        lower_ebnf(&mut ast).unwrap();
+        insert_wrapper_start_nonterms(&mut ast);
+
+        let nullable = compute_nullable(&ast);
+        let first = compute_first(&ast, &nullable);
+        let follow = compute_follow(&ast, &nullable, &first);
+        let parse_table = compute_parse_table(&ast, &nullable, &first, &follow);
+
+        // The conflict is reported against the use of the macro, with its arguments
+        let ((symbol, terminal), productions) = find_conflict(&parse_table).unwrap();
+        assert_eq!((*symbol, *terminal), ("Twice<\"n\">", "\"n\""));
+        assert_eq!(productions.len(), 2);
+    }
+
+    #[test]
+    fn name_selections() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "(" = Token::LParen,
+                ")" = Token::RParen
+            }
+            grammar;
+            pub Start: () = {
+                "(" <Start> ")",
+                "(" ")" => <>,
+                "(" ")" => (),
+            };
+        };
+        lower_ebnf(&mut ast).unwrap();
+
+        let named = |name, symbol| AstSymbol::Named(name, Box::new(symbol));
+        assert_eq!(
+            ast.nonterminals[0]
+                .productions
+                .iter()
+                .map(|production| production.symbols.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![
+                    AstSymbol::Terminal("\"(\""),
+                    named("__1", AstSymbol::Nonterminal("Start")),
+                    AstSymbol::Terminal("\")\""),
+                ],
+                vec![
+                    named("__0", AstSymbol::Terminal("\"(\"")),
+                    named("__1", AstSymbol::Terminal("\")\"")),
+                ],
+                symbols!("(" ")"),
+            ]
+        );
+    }
+
     #[test]
     fn infix_parens() {
         let mut ast = parse_grammar! {
@@ -369,7 +2417,7 @@ mod tests {
                 "(" P ")" => (),
             };
         };
-        insert_wrapper_start_nonterm(&mut ast);
+        insert_wrapper_start_nonterms(&mut ast);
 
         let nullable = compute_nullable(&ast);
         assert_eq!(
@@ -387,7 +2435,7 @@ mod tests {
                 "\"!\"" => false,
                 "\"&&\"" => false,
                 "\"||\"" => false,
-                WRAPPER_NONTERM => false,
+                wrapper_nonterm("P") => false,
                 EOF_TERMINAL => false,
             }
         );
@@ -408,7 +2456,7 @@ mod tests {
                 "\"!\"" => collection! { "\"!\"" },
                 "\"&&\"" => collection! { "\"&&\"" },
                 "\"||\"" => collection! { "\"||\"" },
-                WRAPPER_NONTERM => collection! { "\"var\"", "\"!\"", "\"(\"" },
+                wrapper_nonterm("P") => collection! { "\"var\"", "\"!\"", "\"(\"" },
                 EOF_TERMINAL => collection! { EOF_TERMINAL }
             }
         );
@@ -423,64 +2471,91 @@ mod tests {
                 "A" => collection!{ "\"||\"", "\")\"",EOF_TERMINAL },
                 "AP" => collection!{ "\"||\"", "\")\"",EOF_TERMINAL },
                 "Z" => collection!{ "\"||\"", "\"&&\"", "\")\"",EOF_TERMINAL },
-                WRAPPER_NONTERM => collection!()
+                wrapper_nonterm("P") => collection!()
             }
         );
 
-        let code = "()";
+        let action = AstAction::Code("()");
         assert_eq!(
             compute_parse_table(&ast, &nullable, &first, &follow),
             collection! {
-                (WRAPPER_NONTERM, "\"var\"") => collection!(AstProduction {
+                (wrapper_nonterm("P"), "\"var\"") => collection!(AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
                     symbols: vec![
-                        AstSymbol::Named("result", "P"),
+                        AstSymbol::Named("result", Box::new(AstSymbol::Nonterminal("P"))),
                         AstSymbol::Terminal(EOF_TERMINAL)
                     ],
-                    code: "result"
+                    action: AstAction::Code("result")
                 }),
-                (WRAPPER_NONTERM, "\"!\"") => collection!(AstProduction {
+                (wrapper_nonterm("P"), "\"!\"") => collection!(AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
                     symbols: vec![
-                        AstSymbol::Named("result", "P"),
+                        AstSymbol::Named("result", Box::new(AstSymbol::Nonterminal("P"))),
                         AstSymbol::Terminal(EOF_TERMINAL)
                     ],
-                    code: "result"
+                    action: AstAction::Code("result")
                 }),
-                (WRAPPER_NONTERM, "\"&&\"") => collection!(),
-                (WRAPPER_NONTERM, "\"||\"") => collection!(),
-                (WRAPPER_NONTERM, "\"(\"") => collection!(AstProduction {
+                (wrapper_nonterm("P"), "\"&&\"") => collection!(),
+                (wrapper_nonterm("P"), "\"||\"") => collection!(),
+                (wrapper_nonterm("P"), "\"(\"") => collection!(AstProduction {
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
                     symbols: vec![
-                        AstSymbol::Named("result", "P"),
+                        AstSymbol::Named("result", Box::new(AstSymbol::Nonterminal("P"))),
                         AstSymbol::Terminal(EOF_TERMINAL)
                     ],
-                    code: "result"
+                    action: AstAction::Code("result")
                 }),
-                (WRAPPER_NONTERM, "\")\"") => collection!(),
-                (WRAPPER_NONTERM, EOF_TERMINAL) => collection!(),
+                (wrapper_nonterm("P"), "\")\"") => collection!(),
+                (wrapper_nonterm("P"), EOF_TERMINAL) => collection!(),
 
                 ("P", "\"var\"") => collection!(AstProduction {
-                    symbols: symbols!(O), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(O), action: action.clone()
                 }),
                 ("P", "\"!\"") => collection!(AstProduction {
-                    symbols: symbols!(O), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(O), action: action.clone()
                 }),
                 ("P", "\"&&\"") => collection!(),
                 ("P", "\"||\"") => collection!(),
                 ("P", "\"(\"") => collection!(AstProduction {
-                    symbols: symbols!(O), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(O), action: action.clone()
                 }),
                 ("P", "\")\"") => collection!(),
                 ("P", EOF_TERMINAL) => collection!(),
 
                 ("O", "\"var\"") => collection!(AstProduction {
-                    symbols: symbols!(A OP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(A OP), action: action.clone()
                 }),
                 ("O", "\"!\"") => collection!(AstProduction {
-                    symbols: symbols!(A OP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(A OP), action: action.clone()
                 }),
                 ("O", "\"&&\"") => collection!(),
                 ("O", "\"||\"") => collection!(),
                 ("O", "\"(\"") => collection!(AstProduction {
-                    symbols: symbols!(A OP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(A OP), action: action.clone()
                 }),
                 ("O", "\")\"") => collection!(),
                 ("O", EOF_TERMINAL) => collection!(),
@@ -489,26 +2564,44 @@ mod tests {
                 ("OP", "\"!\"") => collection!(),
                 ("OP", "\"&&\"") => collection!(),
                 ("OP", "\"||\"") => collection!(AstProduction {
-                    symbols: symbols!("||" A OP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!("||" A OP), action: action.clone()
                 }),
                 ("OP", "\"(\"") => collection!(),
                 ("OP", "\")\"") => collection!(AstProduction {
-                    symbols: symbols!(), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(), action: action.clone()
                 }),
                 ("OP", EOF_TERMINAL) => collection!(AstProduction {
-                    symbols: symbols!(), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(), action: action.clone()
                 }),
 
                 ("A", "\"var\"") => collection!(AstProduction {
-                    symbols: symbols!(Z AP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(Z AP), action: action.clone()
                 }),
                 ("A", "\"!\"") => collection!(AstProduction {
-                    symbols: symbols!(Z AP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(Z AP), action: action.clone()
                 }),
                 ("A", "\"&&\"") => collection!(),
                 ("A", "\"||\"") => collection!(),
                 ("A", "\"(\"") => collection!(AstProduction {
-                    symbols: symbols!(Z AP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(Z AP), action: action.clone()
                 }),
                 ("A", "\")\"") => collection!(),
                 ("A", EOF_TERMINAL) => collection!(),
@@ -516,33 +2609,364 @@ mod tests {
                 ("AP", "\"var\"") => collection!(),
                 ("AP", "\"!\"") => collection!(),
                 ("AP", "\"&&\"") => collection!(AstProduction {
-                    symbols: symbols!("&&" Z AP), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!("&&" Z AP), action: action.clone()
                 }),
                 ("AP", "\"||\"") => collection!(AstProduction {
-                    symbols: symbols!(), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(), action: action.clone()
                 }),
                 ("AP", "\"(\"") => collection!(),
                 ("AP", "\")\"") => collection!(AstProduction {
-                    symbols: symbols!(), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(), action: action.clone()
                 }),
                 ("AP", EOF_TERMINAL) => collection!(AstProduction {
-                    symbols: symbols!(), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!(), action: action.clone()
                 }),
 
                 ("Z", "\"var\"") => collection!(AstProduction {
-                    symbols: symbols!("var"), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!("var"), action: action.clone()
                 }),
                 ("Z", "\"!\"") => collection!(AstProduction {
-                    symbols: symbols!("!" Z), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!("!" Z), action: action.clone()
                 }),
                 ("Z", "\"&&\"") => collection!(),
                 ("Z", "\"||\"") => collection!(),
                 ("Z", "\"(\"") => collection!(AstProduction {
-                    symbols: symbols!("(" P ")"), code
+                    docs: Vec::new(),
+                    attrs: Vec::new(),
+                    predicates: Vec::new(),
+                    symbols: symbols!("(" P ")"), action: action.clone()
                 }),
                 ("Z", "\")\"") => collection!(),
                 ("Z", EOF_TERMINAL) => collection!(),
             }
         );
     }
+
+    #[test]
+    fn lower_precedence_levels() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "n" = Token::N,
+                "-" = Token::Minus,
+                "^" = Token::Pow
+            }
+            grammar;
+            pub E: () = {
+                "n" => (),
+                #[precedence(level = 1)]
+                "-" E => (),
+                #[precedence(level = 2, assoc = "right")]
+                E "^" E => (),
+                #[precedence(level = "3", assoc = "left")]
+                E "-" E => (),
+            };
+        };
+        lower_ebnf(&mut ast).unwrap();
+        lower_precedence(&mut ast).unwrap();
+
+        let productions = |name| {
+            ast.nonterminals
+                .iter()
+                .find(|nonterminal| nonterminal.name == name)
+                .unwrap()
+                .productions
+                .iter()
+                .map(|production| production.symbols.clone())
+                .collect::<Vec<_>>()
+        };
+        let nonterm = AstSymbol::Nonterminal;
+        assert_eq!(productions("E"), vec![vec![nonterm("E#3")]]);
+        assert_eq!(
+            productions("E#atom"),
+            vec![vec![AstSymbol::Terminal("\"n\""), nonterm("E#fold0")]]
+        );
+        assert_eq!(
+            productions("E#1"),
+            vec![
+                vec![
+                    AstSymbol::Terminal("\"-\""),
+                    nonterm("E#1"),
+                    nonterm("E#fold1")
+                ],
+                vec![nonterm("E#atom")],
+            ]
+        );
+        assert_eq!(
+            productions("E#2~"),
+            vec![
+                vec![
+                    AstSymbol::Terminal("\"^\""),
+                    nonterm("E#2"),
+                    nonterm("E#fold2")
+                ],
+                Vec::new(),
+            ]
+        );
+        assert_eq!(
+            productions("E#3~"),
+            vec![
+                vec![
+                    AstSymbol::Terminal("\"-\""),
+                    nonterm("E#2"),
+                    nonterm("E#fold3"),
+                    nonterm("E#3~"),
+                ],
+                Vec::new(),
+            ]
+        );
+        assert!(matches!(
+            &ast.nonterminals
+                .iter()
+                .find(|nonterminal| nonterminal.name == "E#fold3")
+                .unwrap()
+                .productions[0]
+                .action,
+            AstAction::Fold("E", production) if production.symbols == symbols!(E "-" E)
+        ));
+    }
+
+    #[test]
+    fn invalid_precedence() {
+        let invalid_grammars = [
+            (
+                r#"#[precedence] E "-" E => (), "n" => ()"#,
+                "Expected arguments in #[precedence]",
+            ),
+            (
+                r#"#[precedence(level)] E "-" E => (), "n" => ()"#,
+                "Expected `key = value` in #[precedence(level)]",
+            ),
+            (
+                r#"#[precedence(level = "high")] E "-" E => (), "n" => ()"#,
+                "Invalid precedence level high",
+            ),
+            (
+                r#"#[precedence(level = 1, assoc = "up")] E "-" E => (), "n" => ()"#,
+                "Invalid associativity up",
+            ),
+            (
+                r#"#[precedence(level = 1, order = 2)] E "-" E => (), "n" => ()"#,
+                "Unknown precedence argument order",
+            ),
+            (
+                r#"#[precedence(assoc = "right")] E "-" E => (), "n" => ()"#,
+                "Expected a level in #[precedence(assoc = \"right\")]",
+            ),
+            (
+                r#"#[precedence(level = 1)] E "-" E => (),
+                #[precedence(level = 1, assoc = "right")] E "+" E => (),
+                "n" => ()"#,
+                "Precedence level 1 of E is declared with different associativities",
+            ),
+            (
+                r#"#[precedence(level = 1)] E "-" E => (), "-" E => ()"#,
+                "E has no operands: at least one production has to be neither infix, prefix \
+                 nor postfix",
+            ),
+        ];
+
+        for (productions, message) in invalid_grammars {
+            let grammar = format!(
+                r#"token Token {{ "n" = Token::N, "-" = Token::Minus, "+" = Token::Plus }}
+                grammar;
+                pub E: () = {{ {} }};"#,
+                productions
+            );
+            let mut ast = crate::parsing::parse(&grammar).unwrap();
+
+            assert_eq!(lower_precedence(&mut ast), Err(message.to_string()));
+        }
+    }
+
+    #[test]
+    fn resolve_conflicts() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "if" = Token::If,
+                "else" = Token::Else,
+                "x" = Token::X
+            }
+            grammar;
+            pub S: () = {
+                "x" => (),
+                #[resolve(shift)]
+                "if" S ("else" S)? => (),
+            };
+            E: () = {
+                #[prefer]
+                "else" => (),
+                => (),
+            };
+            T: () = {
+                "if" E "else" => (),
+                "x" => (),
+            };
+            U: () = {
+                "x" => (),
+                "x" "x" => (),
+            };
+        };
+        lower_ebnf(&mut ast).unwrap();
+        insert_wrapper_start_nonterms(&mut ast);
+
+        let nullable = compute_nullable(&ast);
+        let first = compute_first(&ast, &nullable);
+        let follow = compute_follow(&ast, &nullable, &first);
+        let mut parse_table = compute_parse_table(&ast, &nullable, &first, &follow);
+        let notes = super::resolve_conflicts(&ast, &mut parse_table, &nullable, &first);
+
+        assert_eq!(
+            notes,
+            vec![
+                "Resolved a parse-table conflict at symbol \"(\"else\" S)?\" and terminal \
+                 \"else\" with #[resolve(shift)], choosing: <__value:(\"else\" S)>",
+                "Resolved a parse-table conflict at symbol \"E\" and terminal \"else\" with \
+                 #[prefer], choosing: \"else\"",
+            ]
+        );
+        let else_symbols = parse_table[&("(\"else\" S)?", "\"else\"")]
+            .iter()
+            .map(|production| production.symbols.len())
+            .collect::<Vec<_>>();
+        assert_eq!(else_symbols, vec![1]);
+        assert_eq!(
+            parse_table[&("E", "\"else\"")]
+                .iter()
+                .map(|production| production.symbols.clone())
+                .collect::<Vec<_>>(),
+            vec![vec![AstSymbol::Terminal("\"else\"")]]
+        );
+        // Conflicts which aren't annotated are left alone
+        assert_eq!(parse_table[&("U", "\"x\"")].len(), 2);
+        assert!(find_conflict(&parse_table).is_some());
+    }
+
+    #[test]
+    fn resolution_notes() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "if" = Token::If,
+                "else" = Token::Else,
+                "x" = Token::X
+            }
+            grammar;
+            pub Statement: () = {
+                "x" => (),
+                #[resolve(shift)]
+                "if" "x" Statement ("else" <Statement>)? => (),
+            };
+            pub PreferredStatement: () = {
+                "x" => (),
+                "if" "x" PreferredStatement Else => (),
+            };
+            Else: () = {
+                #[prefer]
+                "else" PreferredStatement => (),
+                => (),
+            };
+        };
+        lower_ebnf(&mut ast).unwrap();
+        insert_wrapper_start_nonterms(&mut ast);
+
+        let nullable = compute_nullable(&ast);
+        let first = compute_first(&ast, &nullable);
+        let follow = compute_follow(&ast, &nullable, &first);
+        let mut parse_table = compute_parse_table(&ast, &nullable, &first, &follow);
+        let notes = super::resolve_conflicts(&ast, &mut parse_table, &nullable, &first);
+
+        // Each note names the conflicting cell and the production chosen for it
+        assert_eq!(
+            notes,
+            vec![
+                "Resolved a parse-table conflict at symbol \"(\"else\" <__1:Statement>)?\" and \
+                 terminal \"else\" with #[resolve(shift)], choosing: \
+                 <__value:(\"else\" <__1:Statement>)>",
+                "Resolved a parse-table conflict at symbol \"Else\" and terminal \"else\" with \
+                 #[prefer], choosing: \"else\" PreferredStatement",
+            ]
+        );
+        assert!(find_conflict(&parse_table).is_none());
+    }
+
+    #[test]
+    fn resolve_attrs_on_shared_nonterminals() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "if" = Token::If,
+                "else" = Token::Else,
+                "x" = Token::X
+            }
+            grammar;
+            pub S: () = {
+                "x" => (),
+                #[resolve(shift)]
+                "if" S ("else" S)? => (),
+                "if" "if" S ("else" S)? => (),
+            };
+            T: () = {
+                #[resolve(shift)]
+                "x" T ("else" T)? => (),
+                => (),
+            };
+        };
+        lower_ebnf(&mut ast).unwrap();
+
+        let resolve_attrs = |name| {
+            ast.nonterminals
+                .iter()
+                .find(|nonterminal| nonterminal.name == name)
+                .unwrap()
+                .attrs
+                .clone()
+        };
+        // The optional `else` of `S` is also used by a production which isn't
+        // annotated
+        assert_eq!(resolve_attrs("(\"else\" S)?"), Vec::new());
+        assert_eq!(resolve_attrs("(\"else\" S)"), Vec::new());
+        assert_eq!(
+            resolve_attrs("(\"else\" T)?"),
+            vec![AstAttribute::new("resolve(shift)")]
+        );
+        assert_eq!(
+            resolve_attrs("(\"else\" T)"),
+            vec![AstAttribute::new("resolve(shift)")]
+        );
+    }
+
+    #[test]
+    fn invalid_resolve_attr() {
+        let mut ast = parse_grammar! {
+            token Token {
+                "x" = Token::X
+            }
+            grammar;
+            pub S: () = {
+                #[resolve(reduce)]
+                "x" => (),
+            };
+        };
+
+        assert_eq!(
+            lower_ebnf(&mut ast),
+            Err("Expected #[resolve(shift)], found #[resolve(reduce)]".to_string())
+        );
+    }
 }
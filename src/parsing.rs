@@ -10,7 +10,9 @@ lalrpop_mod!(
 );
 
 /// Parse a grammar. If there are errors during parsing, the error will be returned.
-pub fn parse(grammar_str: &str) -> Result<AstGrammar, ParseError<usize, Token, Range<usize>>> {
+pub fn parse(
+    grammar_str: &str,
+) -> Result<AstGrammar<'_>, ParseError<usize, Token<'_>, Range<usize>>> {
     let lexer = StatefulLexer::new(Token::lexer(grammar_str));
 
     match parser::GrammarParser::new().parse(lexer) {
@@ -0,0 +1,51 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[regex("[a-z]+")]
+    Word(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token<'input> {
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "WORD" = Token::Word(<&'input str>),
+        }
+
+        grammar<'input>;
+
+        pub Items: Vec<String> = Item*;
+
+        Item: String = {
+            <w:"WORD"> => format!("{}, {};", w, ')'),
+            "(" <items:Items> ")" => {
+                let close: char = ')';
+                let joined = items.join(r#"", ""#);
+                format!("{}{}{}", '(', joined, close)
+            },
+        };
+    }
+}
+
+#[test]
+fn literals_in_actions() {
+    let lexer = Lexer::new("a (b c)");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Ok(vec!["a, );".to_string(), r#"(b, );", "c, );)"#.to_string()])
+    );
+}
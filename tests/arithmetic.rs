@@ -52,6 +52,7 @@ mod parser {
             "/" = Token::Div,
             "-" = Token::Sub,
             "+" = Token::Add,
+            error = Token::Error,
         }
 
         grammar;
@@ -186,8 +187,24 @@ fn missing_operation() {
     assert_eq!(
         result,
         Err(parser::ParseError::UnrecognizedToken {
-            expected: vec!["\"*\"", "\"/\""],
+            expected: vec!["*", "/"],
             found: Token::Number
         })
     )
 }
+
+#[test]
+fn invalid_token() {
+    let lexer = Lexer::new("1 + $");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Err(parser::ParseError::InvalidToken(Token::Error)));
+}
+
+#[test]
+fn invalid_token_after_term() {
+    let lexer = Lexer::new("(1 + 2) $");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Err(parser::ParseError::InvalidToken(Token::Error)));
+}
@@ -0,0 +1,70 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("a")]
+    A,
+    #[token("b")]
+    B,
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[cfg(any())]
+pub struct Missing;
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "a" = Token::A,
+            "b" = Token::B,
+        }
+
+        grammar;
+
+        /// Parse a letter
+        #[deny(unused_variables)]
+        pub Letter: char = {
+            #[allow(unused_variables)]
+            #[inline]
+            #[label = "the letter a"]
+            "a" => {
+                let unused = 'b';
+                'a'
+            },
+            #[cfg(any())]
+            "b" => 'b',
+        };
+
+        // Everything generated for a configured out nonterminal is configured
+        // out, so it can use items which don't exist
+        #[cfg(any())]
+        pub Missing: crate::Missing = "b" => crate::Missing;
+    }
+}
+
+#[test]
+fn enabled_production() {
+    let lexer = Lexer::new("a");
+    let result = parser::parse_letter(lexer);
+
+    assert_eq!(result, Ok('a'));
+}
+
+#[test]
+fn configured_out_production() {
+    let lexer = Lexer::new("b");
+    let result = parser::parse_letter(lexer);
+
+    assert!(matches!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            found: Token::B,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,80 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("if")]
+    If,
+    #[token("else")]
+    Else,
+    #[regex("[a-z]")]
+    Ident(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    // The resolved conflicts are reported as deprecation warnings. Their notes
+    // are checked by the `resolution_notes` test in `ll_table_gen`.
+    #![allow(deprecated)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token<'input> {
+            "if" = Token::If,
+            "else" = Token::Else,
+            "IDENT" = Token::Ident(<&'input str>),
+        }
+
+        grammar<'input>;
+
+        /// Parse a statement, showing which `if` each `else` belongs to
+        pub Statement: String = {
+            <name:"IDENT"> => name.to_string(),
+            #[resolve(shift)]
+            "if" <condition:"IDENT"> <then:Statement> <otherwise:("else" <Statement>)?>
+            => match otherwise {
+                Some(otherwise) => format!("(if {} {} else {})", condition, then, otherwise),
+                None => format!("(if {} {})", condition, then),
+            },
+        };
+
+        /// The same statements, with the `else` branch written out
+        pub PreferredStatement: String = {
+            <name:"IDENT"> => name.to_string(),
+            "if" <condition:"IDENT"> <then:PreferredStatement> <otherwise:Else>
+            => match otherwise {
+                Some(otherwise) => format!("(if {} {} else {})", condition, then, otherwise),
+                None => format!("(if {} {})", condition, then),
+            },
+        };
+
+        Else: Option<String> = {
+            #[prefer]
+            "else" <otherwise:PreferredStatement> => Some(otherwise),
+            => None,
+        };
+    }
+}
+
+#[test]
+fn resolve_shift() {
+    let result = parser::parse_statement(Lexer::new("if a if b x else y"));
+
+    assert_eq!(result, Ok("(if a (if b x else y))".to_string()));
+}
+
+#[test]
+fn prefer() {
+    let result = parser::parse_preferred_statement(Lexer::new("if a if b x else y"));
+
+    assert_eq!(result, Ok("(if a (if b x else y))".to_string()));
+}
+
+#[test]
+fn else_for_each_if() {
+    let result = parser::parse_statement(Lexer::new("if a if b x else y else z"));
+
+    assert_eq!(result, Ok("(if a (if b x else y) else z)".to_string()));
+}
@@ -0,0 +1,124 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("=")]
+    Equals,
+    #[token(";")]
+    Semicolon,
+    #[token("+")]
+    Plus,
+    #[regex("[a-z]+", |lex| lex.slice().to_string())]
+    Ident(String),
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Variable(String),
+    Add(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Statement {
+    name: String,
+    value: Expr,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Expr, Statement, Token};
+
+        token Token {
+            "=" = Token::Equals,
+            ";" = Token::Semicolon,
+            "+" = Token::Plus,
+            "IDENT" = Token::Ident(<String>),
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub StatementList: Vec<Statement> = <Statement*>;
+
+        pub Statement: Statement = <name:"IDENT"> "=" <value:Expr> ";" => Statement { name, value };
+
+        pub Expr: Expr = <first:Atom> <rest:("+" <Atom>)*> => {
+            rest.into_iter()
+                .fold(first, |left, right| Expr::Add(Box::new(left), Box::new(right)))
+        };
+
+        Atom: Expr = {
+            "NUMBER" => Expr::Number(<>),
+            "IDENT" => Expr::Variable(<>),
+        };
+    }
+}
+
+#[test]
+fn statement_list() {
+    let lexer = Lexer::new("x = 1; y = x + 2;");
+    let result = parser::parse_statement_list(lexer);
+
+    assert_eq!(
+        result,
+        Ok(vec![
+            Statement {
+                name: "x".to_string(),
+                value: Expr::Number(1)
+            },
+            Statement {
+                name: "y".to_string(),
+                value: Expr::Add(
+                    Box::new(Expr::Variable("x".to_string())),
+                    Box::new(Expr::Number(2))
+                )
+            },
+        ])
+    );
+}
+
+#[test]
+fn single_statement() {
+    let lexer = Lexer::new("x = 1;");
+    let result = parser::parse_statement(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Statement {
+            name: "x".to_string(),
+            value: Expr::Number(1)
+        })
+    );
+}
+
+#[test]
+fn expression() {
+    let lexer = Lexer::new("1 + x");
+    let result = parser::parse_expr(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Expr::Add(
+            Box::new(Expr::Number(1)),
+            Box::new(Expr::Variable("x".to_string()))
+        ))
+    );
+}
+
+#[test]
+fn expression_then_eof() {
+    let lexer = Lexer::new("1 + x;");
+    let result = parser::parse_expr(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::ExtraToken(Token::Semicolon))
+    );
+}
@@ -0,0 +1,86 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("+")]
+    Add,
+    #[regex("[0-9]+")]
+    Number(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum SumError {
+    InvalidNumber(String),
+    Overflow,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{SumError, Token};
+
+        token Token<'input> {
+            "+" = Token::Add,
+            "NUMBER" = Token::Number(<&'input str>),
+        }
+
+        grammar<'input>;
+
+        extern {
+            type Error = SumError;
+        }
+
+        pub Sum: u8 = <n:Number> <rest:("+" <Number>)*> =>? {
+            rest.into_iter()
+                .try_fold(n, |sum, n| sum.checked_add(n))
+                .ok_or(SumError::Overflow)
+        };
+
+        Number: u8 = <n:"NUMBER"> =>? n.parse().map_err(|_| SumError::InvalidNumber(n.to_string()));
+    }
+}
+
+#[test]
+fn successful_actions() {
+    let lexer = Lexer::new("1 + 20 + 100");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(121));
+}
+
+#[test]
+fn error_in_nested_action() {
+    let lexer = Lexer::new("1 + 256 + 2");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::User {
+            error: SumError::InvalidNumber("256".to_string())
+        })
+    );
+}
+
+#[test]
+fn error_in_start_action() {
+    let lexer = Lexer::new("200 + 100");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::User {
+            error: SumError::Overflow
+        })
+    );
+}
+
+#[test]
+fn syntax_errors_still_reported() {
+    let lexer = Lexer::new("1 +");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Err(parser::ParseError::UnexpectedEOF));
+}
@@ -0,0 +1,124 @@
+use logos::Logos;
+
+#[derive(Logos, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Token {
+    #[token("+")]
+    Add,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct LexError(char);
+
+/// Lex the input, reporting invalid characters as errors
+fn lex(input: &str) -> impl Iterator<Item = Result<Token, LexError>> + '_ {
+    let mut lexer = Token::lexer(input);
+    std::iter::from_fn(move || {
+        let token = lexer.next()?;
+        Some(match token {
+            Token::Error => Err(LexError(lexer.slice().chars().next().unwrap())),
+            token => Ok(token),
+        })
+    })
+}
+
+/// Lex the input into spanned tokens, reporting invalid characters as errors
+fn lex_spanned(input: &str) -> impl Iterator<Item = Result<(usize, Token, usize), LexError>> + '_ {
+    Token::lexer(input)
+        .spanned()
+        .map(move |(token, span)| match token {
+            Token::Error => Err(LexError(input[span].chars().next().unwrap())),
+            token => Ok((span.start, token, span.end)),
+        })
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{LexError, Token};
+
+        token Token {
+            "+" = Token::Add,
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        extern {
+            type LexerError = LexError;
+        }
+
+        pub Sum: i64 = <first:"NUMBER"> <rest:("+" <"NUMBER">)*> => first + rest.iter().sum::<i64>();
+    }
+}
+
+mod spanned_parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{LexError, Token};
+
+        token Token {
+            "+" = Token::Add,
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        extern {
+            type Location = usize;
+            type LexerError = LexError;
+        }
+
+        pub Sum: i64 = <first:"NUMBER"> <rest:("+" <"NUMBER">)*> => first + rest.iter().sum::<i64>();
+    }
+}
+
+#[test]
+fn valid_input() {
+    assert_eq!(parser::parse(lex("1 + 2 + 3")), Ok(6));
+    assert_eq!(spanned_parser::parse(lex_spanned("1 + 2 + 3")), Ok(6));
+}
+
+#[test]
+fn lexer_error() {
+    let result = parser::parse(lex("1 + $"));
+
+    assert_eq!(result, Err(parser::ParseError::Lexer(LexError('$'))));
+}
+
+#[test]
+fn lexer_error_in_lookahead() {
+    let result = parser::parse(lex("1 $"));
+
+    assert_eq!(result, Err(parser::ParseError::Lexer(LexError('$'))));
+}
+
+#[test]
+fn spanned_lexer_error() {
+    let result = spanned_parser::parse(lex_spanned("1 + 2 # 3"));
+
+    assert_eq!(
+        result,
+        Err(spanned_parser::ParseError::Lexer {
+            error: LexError('#'),
+            location: 5
+        })
+    );
+}
+
+#[test]
+fn syntax_error_after_valid_tokens() {
+    let result = spanned_parser::parse(lex_spanned("1 + +"));
+
+    assert_eq!(
+        result,
+        Err(spanned_parser::ParseError::UnrecognizedToken {
+            expected: vec!["NUMBER"],
+            found: (4, Token::Add, 5)
+        })
+    );
+}
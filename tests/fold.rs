@@ -0,0 +1,173 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("*")]
+    Mul,
+    #[token("/")]
+    Div,
+    #[token("+")]
+    Add,
+    #[token("-")]
+    Sub,
+    #[token(")")]
+    RParen,
+    #[token("(")]
+    LParen,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AstTerm {
+    Number(i64),
+    Calculated {
+        operation: AstOperation,
+        left: Box<AstTerm>,
+        right: Box<AstTerm>,
+    },
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum AstOperation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+fn calculated(operation: AstOperation, left: AstTerm, right: AstTerm) -> AstTerm {
+    AstTerm::Calculated {
+        operation,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{calculated, Token, AstTerm, AstOperation};
+
+        token Token {
+            "NUMBER" = Token::Number(<i64>),
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "*" = Token::Mul,
+            "/" = Token::Div,
+            "-" = Token::Sub,
+            "+" = Token::Add,
+        }
+
+        grammar;
+
+        extern {
+            type Error = &'static str;
+        }
+
+        pub Term0: AstTerm =
+            fold(init: Term1, (Operation0 Term1) => |left, (operation, right)| {
+                calculated(operation, left, right)
+            });
+
+        Operation0: AstOperation = {
+            "+" => AstOperation::Add,
+            "-" => AstOperation::Subtract,
+        };
+
+        Term1: AstTerm =
+            fold(Term2, (Operation1 Term2) => |left, (operation, right)| calculated(operation, left, right));
+
+        Operation1: AstOperation = {
+            "*" => AstOperation::Multiply,
+            "/" => AstOperation::Divide,
+        };
+
+        Term2: AstTerm = {
+            <n:"NUMBER"> => AstTerm::Number(n),
+            "(" <inner:Term0> ")" => inner,
+        };
+
+        /// Subtract the numbers, failing on a negative result
+        pub Difference: i64 = fold(
+            <"NUMBER">,
+            ("-" <"NUMBER">) =>? |difference: i64, n| match difference - n {
+                difference if difference < 0 => Err("negative"),
+                difference => Ok(difference),
+            }
+        );
+
+        /// `fold` is only a keyword when it starts a fold
+        pub Number: i64 = <fold:"NUMBER"> => fold;
+    }
+}
+
+use AstOperation::*;
+use AstTerm::Number;
+
+#[test]
+fn number() {
+    let result = parser::parse_term0(Lexer::new("1"));
+
+    assert_eq!(result, Ok(Number(1)));
+}
+
+#[test]
+fn left_associative() {
+    let result = parser::parse_term0(Lexer::new("1 - 2 + 3"));
+
+    assert_eq!(
+        result,
+        Ok(calculated(
+            Add,
+            calculated(Subtract, Number(1), Number(2)),
+            Number(3)
+        ))
+    );
+}
+
+#[test]
+fn nested_folds() {
+    let result = parser::parse_term0(Lexer::new("1 / 2 / (3 - 4) * 5 - 6"));
+
+    assert_eq!(
+        result,
+        Ok(calculated(
+            Subtract,
+            calculated(
+                Multiply,
+                calculated(
+                    Divide,
+                    calculated(Divide, Number(1), Number(2)),
+                    calculated(Subtract, Number(3), Number(4))
+                ),
+                Number(5)
+            ),
+            Number(6)
+        ))
+    );
+}
+
+#[test]
+fn missing_operand() {
+    let result = parser::parse_term0(Lexer::new("1 + "));
+
+    assert_eq!(result, Err(parser::ParseError::UnexpectedEOF));
+}
+
+#[test]
+fn fallible_fold() {
+    assert_eq!(parser::parse_difference(Lexer::new("10 - 3 - 2")), Ok(5));
+    assert_eq!(
+        parser::parse_difference(Lexer::new("10 - 30 - 2")),
+        Err(parser::ParseError::User { error: "negative" })
+    );
+}
+
+#[test]
+fn fold_as_name() {
+    assert_eq!(parser::parse_number(Lexer::new("7")), Ok(7));
+}
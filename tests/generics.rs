@@ -0,0 +1,70 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("=")]
+    Equal,
+    #[token(";")]
+    Semicolon,
+    #[regex("[a-z]+")]
+    Ident(&'input str),
+    #[regex("[0-9]+")]
+    Number(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+        use std::fmt::Debug;
+        use std::str::FromStr;
+
+        token Token<'input> {
+            "=" = Token::Equal,
+            ";" = Token::Semicolon,
+            "IDENT" = Token::Ident(<&'input str>),
+            "NUMBER" = Token::Number(<&'input str>),
+        }
+
+        grammar<'input, T: FromStr> where T::Err: Debug;
+
+        pub Assignments: Vec<(&'input str, T)> = Assignment*;
+
+        Assignment: (&'input str, T) = <name:"IDENT"> "=" <value:Value> ";" => (name, value);
+
+        Value: T = <n:"NUMBER"> => n.parse().unwrap();
+    }
+}
+
+#[test]
+fn borrowed_payloads() {
+    let lexer = Lexer::new("a = 1; bc = 23;");
+    let result = parser::parse::<i32>(lexer);
+
+    assert_eq!(result, Ok(vec![("a", 1), ("bc", 23)]));
+}
+
+#[test]
+fn generic_value_type() {
+    let lexer = Lexer::new("x = 255;");
+    let result = parser::parse::<u8>(lexer);
+
+    assert_eq!(result, Ok(vec![("x", 255)]));
+}
+
+#[test]
+fn borrowed_token_in_error() {
+    let lexer = Lexer::new("a = b;");
+    let result = parser::parse::<i32>(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["NUMBER"],
+            found: Token::Ident("b")
+        })
+    );
+}
@@ -0,0 +1,66 @@
+use logos::{Lexer, Logos};
+use std::collections::HashMap;
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token(",")]
+    Comma,
+    #[regex("[a-z]+")]
+    Ident(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+/// Interns identifiers, giving each distinct one an ID
+#[derive(Default)]
+pub struct Interner {
+    ids: HashMap<String, usize>,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> usize {
+        let next_id = self.ids.len();
+        *self.ids.entry(name.to_string()).or_insert(next_id)
+    }
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Interner, Token};
+
+        token Token<'input> {
+            "," = Token::Comma,
+            "IDENT" = Token::Ident(<&'input str>),
+        }
+
+        grammar<'input>(interner: &mut Interner, offset: usize);
+
+        pub Names: Vec<usize> = <first:Name> <rest:("," <Name>)*> => {
+            let mut names = vec![first];
+            names.extend(rest);
+            names
+        };
+
+        Name: usize = <name:"IDENT"> => interner.intern(name) + offset;
+    }
+}
+
+#[test]
+fn params_reach_actions() {
+    let mut interner = Interner::default();
+    let lexer = Lexer::new("a, b, a, c");
+    let result = parser::parse(&mut interner, 0, lexer);
+
+    assert_eq!(result, Ok(vec![0, 1, 0, 2]));
+}
+
+#[test]
+fn params_persist_between_parses() {
+    let mut interner = Interner::default();
+    parser::parse(&mut interner, 0, Lexer::new("x, y")).unwrap();
+    let result = parser::parse(&mut interner, 10, Lexer::new("y, z"));
+
+    assert_eq!(result, Ok(vec![11, 12]));
+}
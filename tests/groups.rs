@@ -0,0 +1,104 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[token(",")]
+    Comma,
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[regex("[a-z]+", |lex| lex.slice().to_string())]
+    Ident(String),
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Call(String, Vec<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Expr, Token};
+
+        token Token {
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "," = Token::Comma,
+            "+" = Token::Plus,
+            "-" = Token::Minus,
+            "IDENT" = Token::Ident(<String>),
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub Expr: Expr = <first:Term> <rest:(<op:("+" | "-")> <term:Term>)*> => {
+            rest.into_iter().fold(first, |left, (op, right)| match op {
+                Token::Plus => Expr::Add(Box::new(left), Box::new(right)),
+                _ => Expr::Sub(Box::new(left), Box::new(right)),
+            })
+        };
+
+        Term: Expr = {
+            <n:"NUMBER"> => Expr::Number(n),
+            <name:"IDENT"> "(" <args:(<first:Expr> <rest:("," <e:Expr>)*>)?> ")" => {
+                let args = match args {
+                    Some((first, rest)) => std::iter::once(first).chain(rest).collect(),
+                    None => Vec::new(),
+                };
+                Expr::Call(name, args)
+            },
+        };
+    }
+}
+
+#[test]
+fn alternatives() {
+    let lexer = Lexer::new("1 + 2 - 3");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Expr::Sub(
+            Box::new(Expr::Add(
+                Box::new(Expr::Number(1)),
+                Box::new(Expr::Number(2))
+            )),
+            Box::new(Expr::Number(3))
+        ))
+    );
+}
+
+#[test]
+fn optional_group() {
+    let lexer = Lexer::new("f() + g(1, h(2))");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Expr::Add(
+            Box::new(Expr::Call("f".to_string(), Vec::new())),
+            Box::new(Expr::Call(
+                "g".to_string(),
+                vec![
+                    Expr::Number(1),
+                    Expr::Call("h".to_string(), vec![Expr::Number(2)])
+                ]
+            ))
+        ))
+    );
+}
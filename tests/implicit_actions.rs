@@ -0,0 +1,103 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[token(",")]
+    Comma,
+    #[token("=")]
+    Equals,
+    #[regex("[a-z]+", |lex| lex.slice().to_string())]
+    Ident(String),
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Assignment {
+    name: String,
+    value: Value,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Value {
+    Number(i64),
+    Pair(Box<Value>, Box<Value>),
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Assignment, Token, Value};
+
+        token Token {
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "," = Token::Comma,
+            "=" = Token::Equals,
+            "IDENT" = Token::Ident(<String>),
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub Assignment: Assignment = <name:"IDENT"> "=" <value:Value> => Assignment { name, value };
+
+        Value: Value = {
+            "NUMBER" => Value::Number(<>),
+            "(" <Pair> ")" => Value::Pair(Box::new(<>.0), Box::new(<>.1)),
+        };
+
+        Pair: (Value, Value) = <Value> "," <Value>;
+    }
+}
+
+mod placeholder_parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        // Only the `<>` outside of the string literal is replaced
+        pub Described: String = "NUMBER" => format!("<>={}", <>);
+    }
+}
+
+#[test]
+fn implicit_values() {
+    let lexer = Lexer::new("x = (1, (2, 3))");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Assignment {
+            name: "x".to_string(),
+            value: Value::Pair(
+                Box::new(Value::Number(1)),
+                Box::new(Value::Pair(
+                    Box::new(Value::Number(2)),
+                    Box::new(Value::Number(3))
+                ))
+            )
+        })
+    );
+}
+
+#[test]
+fn placeholders_in_literals() {
+    let lexer = Lexer::new("5");
+    let result = placeholder_parser::parse(lexer);
+
+    assert_eq!(result, Ok("<>=5".to_string()));
+}
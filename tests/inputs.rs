@@ -0,0 +1,135 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("int")]
+    Int,
+    #[token("char")]
+    Char,
+    #[token("*")]
+    Star,
+    #[token(",")]
+    Comma,
+    #[token(";")]
+    Semicolon,
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(usize),
+    #[regex("[a-z]+")]
+    Ident(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    Int,
+    Char,
+    Pointer(Box<Type>),
+    Array(Box<Type>, usize),
+}
+
+fn pointer(ty: &Type) -> Type {
+    Type::Pointer(Box::new(ty.clone()))
+}
+
+fn array(ty: &Type, len: usize) -> Type {
+    Type::Array(Box::new(ty.clone()), len)
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{array, pointer, Token, Type};
+
+        token Token<'input> {
+            "int" = Token::Int,
+            "char" = Token::Char,
+            "*" = Token::Star,
+            "," = Token::Comma,
+            ";" = Token::Semicolon,
+            "[" = Token::LBracket,
+            "]" = Token::RBracket,
+            "NUMBER" = Token::Number(<usize>),
+            "IDENT" = Token::Ident(<&'input str>),
+        }
+
+        grammar<'input>;
+
+        /// Parse a declaration, giving the type of each declared name
+        pub Declaration: Vec<(&'input str, Type)> =
+            <base:BaseType> <declarators:Declarators(base.clone())> ";" => declarators;
+
+        BaseType: Type = {
+            "int" => Type::Int,
+            "char" => Type::Char,
+        };
+
+        Declarators(base: Type): Vec<(&'input str, Type)> =
+            <first:Declarator(base.clone())> <rest:("," <Declarator(base.clone())>)*> => {
+                let mut declarators = vec![first];
+                declarators.extend(rest);
+                declarators
+            };
+
+        Declarator(ty: Type): (&'input str, Type) = {
+            "*" <declarator:Declarator(pointer(ty))> => declarator,
+            <name:"IDENT"> <declarator:ArraySuffix(*name, ty.clone())> => declarator,
+        };
+
+        ArraySuffix(name: &'input str, ty: Type): (&'input str, Type) = {
+            "[" <len:"NUMBER"> "]" <declarator:ArraySuffix(*name, array(ty, *len))>
+            => declarator,
+            => (*name, ty.clone()),
+        };
+    }
+}
+
+fn parse(input: &str) -> Vec<(&str, Type)> {
+    parser::parse(Lexer::new(input)).unwrap()
+}
+
+#[test]
+fn single_declarator() {
+    assert_eq!(parse("int a;"), vec![("a", Type::Int)]);
+}
+
+#[test]
+fn base_type_applies_to_each_declarator() {
+    assert_eq!(
+        parse("char a, *b, **c;"),
+        vec![
+            ("a", Type::Char),
+            ("b", pointer(&Type::Char)),
+            ("c", pointer(&pointer(&Type::Char))),
+        ]
+    );
+}
+
+#[test]
+fn inputs_from_earlier_symbols() {
+    assert_eq!(
+        parse("int *a[2][3], b[4];"),
+        vec![
+            ("a", array(&array(&pointer(&Type::Int), 2), 3)),
+            ("b", array(&Type::Int, 4)),
+        ]
+    );
+}
+
+#[test]
+fn syntax_error_in_declarator() {
+    let result = parser::parse(Lexer::new("int *;"));
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["*", "IDENT"],
+            found: Token::Semicolon,
+        })
+    );
+}
@@ -0,0 +1,77 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[token(",")]
+    Comma,
+    #[regex("[a-z]+")]
+    Ident,
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Node {
+    Ident(usize, usize),
+    Call(usize, usize, Vec<Node>),
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Node, Token};
+
+        token Token {
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "," = Token::Comma,
+            "IDENT" = Token::Ident,
+        }
+
+        grammar;
+
+        pub Expr: Node = <l:@L> "IDENT" <args:Args?> <r:@R> => match args {
+            Some(args) => Node::Call(l, r, args),
+            None => Node::Ident(l, r),
+        };
+
+        Args: Vec<Node> = "(" <first:Expr> <rest:("," <Expr>)*> ")" => {
+            let mut args = vec![first];
+            args.extend(rest);
+            args
+        };
+
+        /// An empty production is located at the end of the previous symbol
+        pub Empty: (usize, usize) = "IDENT" <e:Nothing> => e;
+
+        Nothing: (usize, usize) = <l:@L> <r:@R> => (l, r);
+    }
+}
+
+#[test]
+fn token_spans() {
+    let lexer = Lexer::new("f(a, g(b))");
+    let result = parser::parse_expr(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Node::Call(
+            0,
+            9,
+            vec![Node::Ident(2, 3), Node::Call(4, 8, vec![Node::Ident(6, 7)])]
+        ))
+    );
+}
+
+#[test]
+fn empty_production() {
+    let lexer = Lexer::new("a");
+    let result = parser::parse_empty(lexer);
+
+    assert_eq!(result, Ok((1, 1)));
+}
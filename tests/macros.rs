@@ -0,0 +1,97 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
+    #[token(",")]
+    Comma,
+    #[regex("[a-z]+", |lex| lex.slice().to_string())]
+    Ident(String),
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Value {
+    Number(i64),
+    List(Vec<Value>),
+    Call(String, Vec<Value>),
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Token, Value};
+
+        token Token {
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "[" = Token::LBracket,
+            "]" = Token::RBracket,
+            "," = Token::Comma,
+            "IDENT" = Token::Ident(<String>),
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub Value: Value = {
+            <n:"NUMBER"> => Value::Number(n),
+            <values:Delimited<"[", Comma<Value>, "]">> => Value::List(values),
+            <name:"IDENT"> <args:Delimited<"(", Comma<Value>, ")">> => Value::Call(name, args),
+        };
+
+        Delimited<Open, T, Close>: T = Open <value:T> Close => value;
+
+        Comma<T>: Vec<T> = {
+            <first:T> <rest:("," <e:T>)*> => {
+                let mut items = vec![first];
+                items.extend(rest);
+                items
+            },
+            => Vec::new(),
+        };
+    }
+}
+
+#[test]
+fn nested_macros() {
+    let lexer = Lexer::new("[1, f(2, []), g()]");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Ok(Value::List(vec![
+            Value::Number(1),
+            Value::Call(
+                "f".to_string(),
+                vec![Value::Number(2), Value::List(Vec::new())]
+            ),
+            Value::Call("g".to_string(), Vec::new()),
+        ]))
+    );
+}
+
+#[test]
+fn missing_separator() {
+    let lexer = Lexer::new("f(1 2)");
+    let result = parser::parse(lexer);
+
+    assert!(matches!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            found: Token::Number(2),
+            ..
+        })
+    ));
+}
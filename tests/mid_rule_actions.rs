@@ -0,0 +1,113 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("{")]
+    LBrace,
+    #[token("}")]
+    RBrace,
+    #[regex("[a-z]+")]
+    Ident(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+/// Tracks the open scopes, logging when they're opened and closed
+#[derive(Default)]
+pub struct Scopes {
+    open: Vec<usize>,
+    count: usize,
+    log: Vec<String>,
+}
+
+impl Scopes {
+    fn open(&mut self) -> usize {
+        let scope = self.count;
+        self.count += 1;
+        self.open.push(scope);
+        self.log.push(format!("open {}", scope));
+        scope
+    }
+
+    fn close(&mut self) {
+        let scope = self.open.pop().unwrap();
+        self.log.push(format!("close {}", scope));
+    }
+
+    fn declare(&mut self, name: &str) -> usize {
+        let scope = *self.open.last().unwrap();
+        self.log.push(format!("declare {} in {}", name, scope));
+        scope
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Item<'input> {
+    Declaration(&'input str, usize),
+    Block(usize, Vec<Item<'input>>),
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Item, Scopes, Token};
+
+        token Token<'input> {
+            "{" = Token::LBrace,
+            "}" = Token::RBrace,
+            "IDENT" = Token::Ident(<&'input str>),
+        }
+
+        grammar<'input>(scopes: &mut Scopes);
+
+        pub Block: Item<'input> = {
+            "{" <scope:{ scopes.open() }: usize> <items:Item*> "}" { scopes.close() }
+            => Item::Block(scope, items),
+        };
+
+        Item: Item<'input> = {
+            <name:"IDENT"> => Item::Declaration(name, scopes.declare(name)),
+            Block,
+        };
+    }
+}
+
+#[test]
+fn actions_run_before_the_rest_of_the_production() {
+    let mut scopes = Scopes::default();
+    let result = parser::parse(&mut scopes, Lexer::new("{ a { b } c }"));
+
+    assert_eq!(
+        result,
+        Ok(Item::Block(
+            0,
+            vec![
+                Item::Declaration("a", 0),
+                Item::Block(1, vec![Item::Declaration("b", 1)]),
+                Item::Declaration("c", 0),
+            ]
+        ))
+    );
+    assert_eq!(
+        scopes.log,
+        vec![
+            "open 0",
+            "declare a in 0",
+            "open 1",
+            "declare b in 1",
+            "close 1",
+            "declare c in 0",
+            "close 0",
+        ]
+    );
+}
+
+#[test]
+fn syntax_error_after_action() {
+    let mut scopes = Scopes::default();
+    let result = parser::parse(&mut scopes, Lexer::new("{ a"));
+
+    assert_eq!(result, Err(parser::ParseError::UnexpectedEOF));
+    assert_eq!(scopes.log, vec!["open 0", "declare a in 0"]);
+}
@@ -111,7 +111,7 @@ fn missing_number() {
     assert_eq!(
         result,
         Err(parser::ParseError::UnrecognizedToken {
-            expected: vec!["\"(\"", "\"NUMBER\""],
+            expected: vec!["(", "NUMBER"],
             found: Token::RParen
         })
     )
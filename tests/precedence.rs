@@ -0,0 +1,110 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("+")]
+    Add,
+    #[token("-")]
+    Sub,
+    #[token("*")]
+    Mul,
+    #[token("^")]
+    Pow,
+    #[token("!")]
+    Factorial,
+    #[token("==")]
+    Eq,
+    #[token("(")]
+    LParen,
+    #[token(")")]
+    RParen,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "+" = Token::Add,
+            "-" = Token::Sub,
+            "*" = Token::Mul,
+            "^" = Token::Pow,
+            "!" = Token::Factorial,
+            "==" = Token::Eq,
+            "(" = Token::LParen,
+            ")" = Token::RParen,
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        /// Parse an expression, showing how its operators are grouped
+        pub Expr: String = {
+            <n:"NUMBER"> => n.to_string(),
+            "(" <e:Expr> ")" => e,
+            #[precedence(level = 1, assoc = "left")]
+            <e:Expr> "!" => format!("({}!)", e),
+            #[precedence(level = 2)]
+            "-" <e:Expr> => format!("(-{})", e),
+            #[precedence(level = 3, assoc = "right")]
+            <l:Expr> "^" <r:Expr> => format!("({} ^ {})", l, r),
+            #[precedence(level = 4, assoc = "left")]
+            <l:Expr> "*" <r:Expr> => format!("({} * {})", l, r),
+            #[precedence(level = 5, assoc = "left")]
+            <l:Expr> "+" <r:Expr> => format!("({} + {})", l, r),
+            <l:Expr> "-" <r:Expr> => format!("({} - {})", l, r),
+            #[precedence(level = 6, assoc = "none")]
+            <l:Expr> "==" <r:Expr> => format!("({} == {})", l, r),
+        };
+    }
+}
+
+fn parse(input: &str) -> String {
+    parser::parse(Lexer::new(input)).unwrap()
+}
+
+#[test]
+fn operand() {
+    assert_eq!(parse("42"), "42");
+    assert_eq!(parse("(42)"), "42");
+}
+
+#[test]
+fn left_associative() {
+    assert_eq!(parse("1 - 2 - 3"), "((1 - 2) - 3)");
+    assert_eq!(parse("1 + 2 - 3 + 4"), "(((1 + 2) - 3) + 4)");
+}
+
+#[test]
+fn right_associative() {
+    assert_eq!(parse("1 ^ 2 ^ 3"), "(1 ^ (2 ^ 3))");
+}
+
+#[test]
+fn tighter_levels_first() {
+    assert_eq!(parse("1 + 2 * 3"), "(1 + (2 * 3))");
+    assert_eq!(parse("1 * 2 + 3"), "((1 * 2) + 3)");
+    assert_eq!(parse("2 * 3 ^ 2 * 4"), "((2 * (3 ^ 2)) * 4)");
+    assert_eq!(parse("(1 + 2) * 3"), "((1 + 2) * 3)");
+}
+
+#[test]
+fn prefix_and_postfix() {
+    assert_eq!(parse("- - 3!"), "(-(-(3!)))");
+    assert_eq!(parse("3!! - -2"), "(((3!)!) - (-2))");
+    assert_eq!(parse("-2 ^ 2"), "((-2) ^ 2)");
+}
+
+#[test]
+fn non_associative() {
+    assert_eq!(parse("1 + 1 == 2"), "((1 + 1) == 2)");
+
+    let result = parser::parse(Lexer::new("1 == 1 == 1"));
+    assert_eq!(result, Err(parser::ParseError::ExtraToken(Token::Eq)));
+}
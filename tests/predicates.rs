@@ -0,0 +1,94 @@
+use logos::{Lexer, Logos};
+use std::collections::HashSet;
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("=")]
+    Assign,
+    #[token(";")]
+    Semicolon,
+    #[regex("[a-z]+")]
+    Ident(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Statement<'input> {
+    Declaration(&'input str, &'input str),
+    Assignment(&'input str, &'input str),
+    Expression(&'input str),
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::{Statement, Token};
+        use std::collections::HashSet;
+
+        token Token<'input> {
+            "=" = Token::Assign,
+            ";" = Token::Semicolon,
+            "IDENT" = Token::Ident(<&'input str>),
+        }
+
+        grammar<'input>(type_names: &HashSet<&'static str>);
+
+        pub Statements: Vec<Statement<'input>> = <Statement*>;
+
+        Statement: Statement<'input> = {
+            // Type names are only known while parsing, like C's typedefs
+            &{ matches!(token, Some(Token::Ident(name)) if type_names.contains(name)) }
+            <ty:"IDENT"> <name:"IDENT"> ";" => Statement::Declaration(ty, name),
+            &("IDENT" "=") <name:"IDENT"> "=" <value:"IDENT"> ";"
+            => Statement::Assignment(name, value),
+            &("IDENT") <value:"IDENT"> ";" => Statement::Expression(value),
+        };
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Statement<'_>>, parser::ParseError<Token<'_>, &'static str>> {
+    let type_names = HashSet::from(["int", "size"]);
+    parser::parse(&type_names, Lexer::new(input))
+}
+
+#[test]
+fn semantic_predicate() {
+    assert_eq!(
+        parse("int a; size b;"),
+        Ok(vec![
+            Statement::Declaration("int", "a"),
+            Statement::Declaration("size", "b"),
+        ])
+    );
+}
+
+#[test]
+fn syntactic_predicate() {
+    assert_eq!(
+        parse("a = b; c; int d;"),
+        Ok(vec![
+            Statement::Assignment("a", "b"),
+            Statement::Expression("c"),
+            Statement::Declaration("int", "d"),
+        ])
+    );
+}
+
+#[test]
+fn predicates_tried_in_order() {
+    // A type name is never parsed as an expression
+    assert_eq!(
+        parse("int;"),
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["IDENT"],
+            found: Token::Semicolon,
+        })
+    );
+}
+
+#[test]
+fn lookahead_past_end_of_input() {
+    assert_eq!(parse("a"), Err(parser::ParseError::UnexpectedEOF));
+}
@@ -0,0 +1,110 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("[")]
+    LBracket,
+    #[token("]")]
+    RBracket,
+    #[token(",")]
+    Comma,
+    #[token("-")]
+    Minus,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "[" = Token::LBracket,
+            "]" = Token::RBracket,
+            "," = Token::Comma,
+            "-" = Token::Minus,
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub Lists: Vec<Vec<i64>> = <lists:List*> => lists;
+
+        List: Vec<i64> = "[" <items:Items?> "]" => items.unwrap_or_default();
+
+        Items: Vec<i64> = <first:Number> <rest:CommaNumber*> => {
+            let mut items = vec![first];
+            items.extend(rest);
+            items
+        };
+
+        CommaNumber: i64 = "," <n:Number> => n;
+
+        Number: i64 = <signs:"-"*> <n:"NUMBER"> => if signs.len() % 2 == 0 { n } else { -n };
+    }
+}
+
+mod non_empty_parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub Numbers: Vec<i64> = <numbers:"NUMBER"+> => numbers;
+    }
+}
+
+#[test]
+fn no_lists() {
+    let lexer = Lexer::new("");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(Vec::new()));
+}
+
+#[test]
+fn lists() {
+    let lexer = Lexer::new("[1, 2, 3] [] [--4, -5]");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(vec![vec![1, 2, 3], Vec::new(), vec![4, -5]]));
+}
+
+#[test]
+fn trailing_comma() {
+    let lexer = Lexer::new("[1,]");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["-", "NUMBER"],
+            found: Token::RBracket
+        })
+    );
+}
+
+#[test]
+fn one_or_more() {
+    let lexer = Lexer::new("1 2 3");
+    let result = non_empty_parser::parse(lexer);
+
+    assert_eq!(result, Ok(vec![1, 2, 3]));
+}
+
+#[test]
+fn one_or_more_empty() {
+    let lexer = Lexer::new("");
+    let result = non_empty_parser::parse(lexer);
+
+    assert_eq!(result, Err(non_empty_parser::ParseError::UnexpectedEOF));
+}
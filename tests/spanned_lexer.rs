@@ -0,0 +1,96 @@
+use logos::Logos;
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("+")]
+    Add,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+/// Lex the input into `(start, token, end)` triples
+fn lex(input: &str) -> impl Iterator<Item = (usize, Token, usize)> + '_ {
+    Token::lexer(input)
+        .spanned()
+        .map(|(token, span)| (span.start, token, span.end))
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "+" = Token::Add,
+            "NUMBER" = Token::Number(<i64>),
+            error = Token::Error,
+        }
+
+        grammar;
+
+        extern {
+            type Location = usize;
+        }
+
+        pub Sum: (i64, std::ops::Range<usize>) =
+            <l:@L> <first:"NUMBER"> <rest:("+" <"NUMBER">)*> <r:@R> => {
+                (first + rest.iter().sum::<i64>(), l..r)
+            };
+    }
+}
+
+#[test]
+fn spans_from_lexer() {
+    let result = parser::parse(lex("  1 + 20  +300 "));
+
+    assert_eq!(result, Ok((321, 2..14)));
+}
+
+#[test]
+fn unrecognized_token_location() {
+    let result = parser::parse(lex("1 + + 2"));
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["NUMBER"],
+            found: (4, Token::Add, 5)
+        })
+    );
+}
+
+#[test]
+fn missing_operator_location() {
+    let result = parser::parse(lex("1 2"));
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["+"],
+            found: (2, Token::Number(2), 3)
+        })
+    );
+}
+
+#[test]
+fn unexpected_eof_location() {
+    let result = parser::parse(lex("1 +  "));
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnexpectedEOF { location: 3 })
+    );
+}
+
+#[test]
+fn invalid_token_location() {
+    let result = parser::parse(lex("1 + ?"));
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::InvalidToken((4, Token::Error, 5)))
+    );
+}
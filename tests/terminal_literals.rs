@@ -0,0 +1,74 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("\"")]
+    Quote,
+    #[token("\\")]
+    Backslash,
+    #[regex("[a-z]+")]
+    Word(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token<'input> {
+            "\"" = Token::Quote,
+            r"\" = Token::Backslash,
+            "word" = Token::Word(<&'input str>),
+            "\x6Eone" = Token::Word("none"),
+        }
+
+        grammar<'input>;
+
+        pub Quoted: Vec<&'input str> = r#"""# <r#match:r#type*> "\u{22}" => r#match;
+
+        r#type: &'input str = {
+            "word",
+            "\x6Eone" => "none",
+            "\\" r"\" => "\\",
+        };
+    }
+}
+
+#[test]
+fn escaped_terminals() {
+    let lexer = Lexer::new(r#"" a \\ none b ""#);
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(vec!["a", "\\", "none", "b"]));
+}
+
+#[test]
+fn canonical_terminal_names() {
+    let lexer = Lexer::new("a");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["\""],
+            found: Token::Word("a")
+        })
+    );
+}
+
+#[test]
+fn unescaped_terminal_names() {
+    let lexer = Lexer::new(r#"" \ a"#);
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["\\"],
+            found: Token::Word("a")
+        })
+    );
+}
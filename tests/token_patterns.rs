@@ -0,0 +1,87 @@
+mod lex {
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    pub enum Tok {
+        Ident(String),
+        Num { value: i64, radix: u32 },
+        Op(char),
+        LParen,
+        RParen,
+        Semicolon,
+        Newline,
+    }
+}
+
+use lex::Tok;
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::lex::Tok;
+
+        token Tok {
+            "IDENT" = crate::lex::Tok::Ident(<String>),
+            "NUM" = Tok::Num { value: <i64>, .. },
+            "SIGN" = Tok::Op('+' | '-'),
+            "(" = Tok::LParen,
+            ")" = Tok::RParen,
+            "END" = Tok::Semicolon | Tok::Newline,
+        }
+
+        grammar;
+
+        pub Statement: (String, i64) =
+            <name:"IDENT"> "(" <value:Value> ")" "END" => (name, value);
+
+        Value: i64 = <sign:"SIGN"> <n:"NUM"> => if sign == Tok::Op('-') { -n } else { n };
+    }
+}
+
+#[test]
+fn data_carrying_tokens() {
+    let tokens = vec![
+        Tok::Ident("print".to_string()),
+        Tok::LParen,
+        Tok::Op('-'),
+        Tok::Num {
+            value: 12,
+            radix: 10,
+        },
+        Tok::RParen,
+        Tok::Newline,
+    ];
+    let result = parser::parse(tokens.into_iter());
+
+    assert_eq!(result, Ok(("print".to_string(), -12)));
+}
+
+#[test]
+fn or_pattern_alternative() {
+    let tokens = vec![
+        Tok::Ident("print".to_string()),
+        Tok::LParen,
+        Tok::Op('+'),
+        Tok::Num {
+            value: 255,
+            radix: 16,
+        },
+        Tok::RParen,
+        Tok::Semicolon,
+    ];
+    let result = parser::parse(tokens.into_iter());
+
+    assert_eq!(result, Ok(("print".to_string(), 255)));
+}
+
+#[test]
+fn literal_in_pattern() {
+    let tokens = vec![Tok::Ident("print".to_string()), Tok::LParen, Tok::Op('*')];
+    let result = parser::parse(tokens.into_iter());
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["SIGN"],
+            found: Tok::Op('*')
+        })
+    );
+}
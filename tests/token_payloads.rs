@@ -0,0 +1,79 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    #[token("+")]
+    Add,
+    #[token("-")]
+    Sub,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i64),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token {
+            "+" = Token::Add,
+            "-" = Token::Sub,
+            "NUMBER" = Token::Number(<i64>),
+        }
+
+        grammar;
+
+        pub Sum: i64 = <n:"NUMBER"> <rest:SumRest> => n + rest;
+
+        SumRest: i64 = {
+            <op:Operator> <sum:Sum> => if op == Token::Add { sum } else { -sum },
+            => 0,
+        };
+
+        Operator: Token = {
+            <op:"+"> => op,
+            <op:"-"> => op,
+        };
+    }
+}
+
+#[test]
+fn single_number() {
+    let lexer = Lexer::new("42");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(42));
+}
+
+#[test]
+fn sum() {
+    let lexer = Lexer::new("1 + 20 + 300");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(321));
+}
+
+#[test]
+fn bound_operator_token() {
+    let lexer = Lexer::new("10 - 3");
+    let result = parser::parse(lexer);
+
+    assert_eq!(result, Ok(7));
+}
+
+#[test]
+fn payload_in_error() {
+    let lexer = Lexer::new("1 2");
+    let result = parser::parse(lexer);
+
+    assert_eq!(
+        result,
+        Err(parser::ParseError::UnrecognizedToken {
+            expected: vec!["+", "-"],
+            found: Token::Number(2)
+        })
+    );
+}
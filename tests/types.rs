@@ -0,0 +1,70 @@
+use logos::{Lexer, Logos};
+
+#[derive(Logos, Debug, Clone, Eq, PartialEq)]
+pub enum Token<'input> {
+    #[token("+")]
+    Plus,
+    #[token("*")]
+    Times,
+    #[regex("[0-9]+", |lex| lex.slice().parse())]
+    Number(i32),
+    #[regex("[a-z]+")]
+    Name(&'input str),
+    #[error]
+    #[regex(r"[ \t\n\f]+", logos::skip)]
+    Error,
+}
+
+mod parser {
+    #![allow(clippy::all)]
+    ll_parser_generator::ll_parser! {
+        use crate::Token;
+
+        token Token<'input> {
+            "+" = Token::Plus,
+            "*" = Token::Times,
+            "NUMBER" = Token::Number(<i32>),
+            "NAME" = Token::Name(<&'input str>),
+        }
+
+        grammar<'input>;
+
+        pub Named: (&'input str, [i32; 2]) = <name:Name> <pair:Pair> => (name, pair);
+
+        Name: &'input str = "NAME";
+
+        Pair: [i32; 2] = <a:Item> <b:Item> => [a, b];
+
+        Item: <Vec<i32> as IntoIterator>::Item = "NUMBER";
+
+        pub Op: Box<dyn Fn(i32) -> i32> = {
+            "+" <n:"NUMBER"> => Box::new(move |x| x + n),
+            "*" <n:"NUMBER"> => Box::new(move |x| x * n),
+        };
+
+        pub Apply: fn(&[i32]) -> Vec<i32> = "NAME" => |xs| xs.iter().rev().copied().collect();
+    }
+}
+
+#[test]
+fn arrays_refs_and_qualified_paths() {
+    let lexer = Lexer::new("pair 1 2");
+    let result = parser::parse_named(lexer);
+
+    assert_eq!(result, Ok(("pair", [1, 2])));
+}
+
+#[test]
+fn trait_objects() {
+    let add = parser::parse_op(Lexer::new("+ 2")).unwrap();
+    let mul = parser::parse_op(Lexer::new("* 3")).unwrap();
+
+    assert_eq!(mul(add(1)), 9);
+}
+
+#[test]
+fn fn_pointers() {
+    let reverse = parser::parse_apply(Lexer::new("reverse")).unwrap();
+
+    assert_eq!(reverse(&[1, 2, 3]), vec![3, 2, 1]);
+}